@@ -0,0 +1,27 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid escape character {got:?}, expected one of ['\\\\', '`']")]
+    EscapeInvalid { got: String },
+    #[error("invalid value for flag --{flag}: {value:?}")]
+    FlagInvalid { flag: String, value: String },
+    #[error("unknown flag --{flag} for {instr}")]
+    FlagUnknown { flag: String, instr: String },
+    #[error("unterminated heredoc, expected {delimiter:?}")]
+    HeredocUnterminated { delimiter: String },
+    #[error("{instr} requires at least {min} argument(s)")]
+    InstrArgsMissing { instr: String, min: usize },
+    #[error("{instr} requires a JSON array of strings")]
+    InstrJsonInvalid { instr: String },
+    #[error("unknown instruction: {instr}")]
+    InstrUnknown { instr: String },
+    #[error("invalid mount option {key:?}")]
+    MountInvalid { key: String },
+    #[error("line {line}: {source}")]
+    Syntax {
+        line: usize,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
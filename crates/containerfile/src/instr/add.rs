@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AddOptions {
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---checksum
+    pub checksum: Option<String>,
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---chown---chmod
+    pub chmod: Option<String>,
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---chown---chmod
+    pub chown: Option<String>,
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---exclude
+    pub exclude: Option<Vec<String>>,
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---keep-git-dir
+    pub keep_git_dir: bool,
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add---link
+    pub link: bool,
+}
+
+impl Display for AddOptions {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let Self {
+            checksum,
+            chmod,
+            chown,
+            exclude,
+            keep_git_dir,
+            link,
+        } = self;
+        let mut args = vec![];
+
+        if let Some(checksum) = checksum {
+            args.push(format!("--checksum={checksum}"));
+        }
+        if let Some(chmod) = chmod {
+            args.push(format!("--chmod={chmod}"));
+        }
+        if let Some(chown) = chown {
+            args.push(format!("--chown={chown}"));
+        }
+        if let Some(exclude) = exclude {
+            for exclude in exclude {
+                args.push(format!("--exclude={exclude}"));
+            }
+        }
+        if *keep_git_dir {
+            args.push(String::from("--keep-git-dir"));
+        }
+        if *link {
+            args.push(String::from("--link"));
+        }
+
+        write!(f, "{}", args.join(" "))?;
+        Ok(())
+    }
+}
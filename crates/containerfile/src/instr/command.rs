@@ -0,0 +1,8 @@
+///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#shell-and-exec-form
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#exec-form
+    Exec(Vec<String>),
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#shell-form
+    Shell(String),
+}
@@ -3,20 +3,20 @@ use std::fmt::Formatter;
 
 use crate::instr::from::FromKind;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct CopyOptions {
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---chown---chmod
     pub chmod: Option<String>,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---chown---chmod
     pub chown: Option<String>,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---exclude
-    pub exclude: Option<String>,
+    pub exclude: Option<Vec<String>>,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---from
     pub from: Option<FromKind>,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---link
     pub link: bool,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy---parents
-    pub parents: Option<String>,
+    pub parents: bool,
 }
 
 impl Display for CopyOptions {
@@ -31,14 +31,16 @@ impl Display for CopyOptions {
         } = self;
         let mut args = vec![];
 
-        if let Some(_chmod) = chmod {
-            unimplemented!();
+        if let Some(chmod) = chmod {
+            args.push(format!("--chmod={chmod}"));
         }
-        if let Some(_chown) = chown {
-            unimplemented!();
+        if let Some(chown) = chown {
+            args.push(format!("--chown={chown}"));
         }
-        if let Some(_exclude) = exclude {
-            unimplemented!();
+        if let Some(exclude) = exclude {
+            for exclude in exclude {
+                args.push(format!("--exclude={exclude}"));
+            }
         }
         if let Some(from) = from {
             args.push(format!("--from={from}"));
@@ -46,8 +48,8 @@ impl Display for CopyOptions {
         if *link {
             args.push(String::from("--link"));
         }
-        if let Some(_parents) = parents {
-            unimplemented!();
+        if *parents {
+            args.push(String::from("--parents"));
         }
 
         write!(f, "{}", args.join(" "))?;
@@ -2,7 +2,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 
 ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#parser-directives
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Directive {
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#check
     Check(String),
@@ -15,9 +15,9 @@ pub enum Directive {
 impl Display for Directive {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self {
-            Directive::Check(_) => unimplemented!(),
+            Directive::Check(check) => write!(f, "# check={check}")?,
             Directive::Escape(c) => write!(f, "# escape={c}")?,
-            Directive::Syntax(_) => unimplemented!(),
+            Directive::Syntax(syntax) => write!(f, "# syntax={syntax}")?,
         }
         Ok(())
     }
@@ -1,7 +1,8 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum FromKind {
     Context(String),
     Image {
@@ -40,3 +41,29 @@ impl Display for FromKind {
         Ok(())
     }
 }
+
+/// Parses an image reference, stage names are resolved by the parser
+impl FromStr for FromKind {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rest, digest) = match s.split_once('@') {
+            Some((rest, digest)) => (rest, Some(digest.to_string())),
+            None => (s, None),
+        };
+        let (repo, rest) = match rest.split_once('/') {
+            Some((repo, rest)) if repo.contains(['.', ':']) || repo == "localhost" => (Some(repo.to_string()), rest),
+            _ => (None, rest),
+        };
+        let (image, tag) = match rest.rsplit_once(':') {
+            Some((image, tag)) if !tag.contains('/') => (image.to_string(), Some(tag.to_string())),
+            _ => (rest.to_string(), None),
+        };
+        Ok(FromKind::Image {
+            digest,
+            image,
+            repo,
+            tag,
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HealthcheckOptions {
+    pub interval: Option<String>,
+    pub retries: Option<u64>,
+    pub start_interval: Option<String>,
+    pub start_period: Option<String>,
+    pub timeout: Option<String>,
+}
+
+impl Display for HealthcheckOptions {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let Self {
+            interval,
+            retries,
+            start_interval,
+            start_period,
+            timeout,
+        } = self;
+        let mut args = vec![];
+
+        if let Some(interval) = interval {
+            args.push(format!("--interval={interval}"));
+        }
+        if let Some(retries) = retries {
+            args.push(format!("--retries={retries}"));
+        }
+        if let Some(start_interval) = start_interval {
+            args.push(format!("--start-interval={start_interval}"));
+        }
+        if let Some(start_period) = start_period {
+            args.push(format!("--start-period={start_period}"));
+        }
+        if let Some(timeout) = timeout {
+            args.push(format!("--timeout={timeout}"));
+        }
+
+        write!(f, "{}", args.join(" "))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#here-documents
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Heredoc {
+    /// `<<-EOF` strips leading tabs from the body and the delimiter line
+    pub chomp: bool,
+    /// Body including the trailing newline of its last line
+    pub content: String,
+    pub delimiter: String,
+    /// `<<"EOF"` and `<<'EOF'` disable variable expansion in the body
+    pub expand: bool,
+}
@@ -1,31 +1,47 @@
+pub mod add;
+pub mod command;
 pub mod copy;
 pub mod directive;
 pub mod from;
+pub mod healthcheck;
+pub mod heredoc;
 pub mod run;
 
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::path::PathBuf;
 
+use crate::instr::add::AddOptions;
+use crate::instr::command::Command;
 use crate::instr::copy::CopyOptions;
 use crate::instr::directive::Directive;
 use crate::instr::from::FromKind;
+use crate::instr::healthcheck::HealthcheckOptions;
+use crate::instr::heredoc::Heredoc;
 use crate::instr::run::RunOptions;
 
 ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#format
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Instr {
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#add
-    Add,
+    Add {
+        ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#destination
+        destination: PathBuf,
+        heredocs: Vec<Heredoc>,
+        options: Option<AddOptions>,
+        ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#source
+        source: Vec<PathBuf>,
+    },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#arg
     Arg(Vec<(String, Option<String>)>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#cmd
-    Cmd,
+    Cmd(Command),
     Comment(String),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#copy
     Copy {
         ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#destination-1
         destination: PathBuf,
+        heredocs: Vec<Heredoc>,
         options: Option<CopyOptions>,
         ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#source-1
         source: Vec<PathBuf>,
@@ -33,11 +49,11 @@ pub enum Instr {
     Directive(Directive),
     Empty,
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#entrypoint
-    Entrypoint,
+    Entrypoint(Command),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#env
     Env(Vec<(String, String)>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#expose
-    Expose,
+    Expose(Vec<String>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#from
     From {
         kind: FromKind,
@@ -45,28 +61,36 @@ pub enum Instr {
         platform: Option<String>,
     },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#healthcheck
-    Healthcheck,
+    Healthcheck {
+        /// `HEALTHCHECK NONE` if unset
+        command: Option<Command>,
+        options: Option<HealthcheckOptions>,
+    },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#label
     Label(Vec<(String, String)>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#maintainer
-    Maintainer,
+    Maintainer(String),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#onbuild
-    Onbuild,
+    Onbuild(Box<Instr>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run
     Run {
-        command: Vec<String>,
+        command: Command,
+        heredocs: Vec<Heredoc>,
         options: Option<RunOptions>,
     },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#shell
-    Shell,
+    Shell(Vec<String>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#stopsignal
-    Stopsignal,
+    Stopsignal(String),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#user
-    User,
+    User {
+        group: Option<String>,
+        user: String,
+    },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#volume
-    Volume,
+    Volume(Vec<String>),
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#workdir
-    Workdir,
+    Workdir(PathBuf),
 }
 
 impl Instr {
@@ -90,11 +114,49 @@ pub struct InstrDisplay<'a> {
     inner: &'a Instr,
 }
 
+impl<'a> InstrDisplay<'a> {
+    fn write_command(&self, f: &mut Formatter, command: &Command) -> std::fmt::Result {
+        match command {
+            Command::Exec(args) => write!(f, " {}", escape_array(self.escape, args)),
+            Command::Shell(script) => write!(f, " {script}"),
+        }
+    }
+
+    fn write_paths(
+        &self,
+        f: &mut Formatter,
+        source: &[PathBuf],
+        destination: &PathBuf,
+        shell: bool,
+    ) -> std::fmt::Result {
+        let mut args = vec![];
+        for path in source.iter().chain([destination]) {
+            args.push(path.to_str().expect("UTF-8").to_string());
+        }
+        if shell {
+            write!(f, " {}", args.join(" "))
+        } else {
+            write!(f, " {}", escape_array(self.escape, &args))
+        }
+    }
+}
+
 impl<'a> Display for InstrDisplay<'a> {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self.inner {
-            Instr::Add => {
-                unimplemented!();
+            Instr::Add {
+                destination,
+                heredocs,
+                options,
+                source,
+            } => {
+                assert!(!source.is_empty());
+                write!(f, "ADD")?;
+                if let Some(options) = options {
+                    write!(f, " {options}")?;
+                }
+                self.write_paths(f, source, destination, !heredocs.is_empty())?;
+                write_heredocs(f, heredocs)?;
             }
             Instr::Arg(inner) => {
                 assert!(!inner.is_empty());
@@ -106,13 +168,14 @@ impl<'a> Display for InstrDisplay<'a> {
                     }
                 }
             }
-            Instr::Cmd => {
+            Instr::Cmd(command) => {
                 write!(f, "CMD")?;
-                unimplemented!();
+                self.write_command(f, command)?;
             }
             Instr::Comment(inner) => write!(f, "# {inner}")?,
             Instr::Copy {
                 destination,
+                heredocs,
                 options,
                 source,
             } => {
@@ -121,18 +184,14 @@ impl<'a> Display for InstrDisplay<'a> {
                 if let Some(options) = options {
                     write!(f, " {options}")?;
                 }
-                let mut args = vec![];
-                for path in source {
-                    args.push(escape_str(self.escape, path.to_str().expect("UTF-8")));
-                }
-                args.push(escape_str(self.escape, destination.to_str().expect("UTF-8")));
-                write!(f, " [{}]", args.join(", "))?;
+                self.write_paths(f, source, destination, !heredocs.is_empty())?;
+                write_heredocs(f, heredocs)?;
             }
             Instr::Directive(directive) => write!(f, "{directive}")?,
             Instr::Empty => {}
-            Instr::Entrypoint => {
+            Instr::Entrypoint(command) => {
                 write!(f, "ENTRYPOINT")?;
-                unimplemented!();
+                self.write_command(f, command)?;
             }
             Instr::Env(inner) => {
                 assert!(!inner.is_empty());
@@ -141,9 +200,9 @@ impl<'a> Display for InstrDisplay<'a> {
                     write!(f, " {key}={}", escape_str(self.escape, value))?;
                 }
             }
-            Instr::Expose => {
-                write!(f, "EXPOSE")?;
-                unimplemented!();
+            Instr::Expose(inner) => {
+                assert!(!inner.is_empty());
+                write!(f, "EXPOSE {}", inner.join(" "))?;
             }
             Instr::From { kind, name, platform } => {
                 write!(f, "FROM")?;
@@ -155,9 +214,18 @@ impl<'a> Display for InstrDisplay<'a> {
                     write!(f, " AS {name}")?;
                 }
             }
-            Instr::Healthcheck => {
+            Instr::Healthcheck { command, options } => {
                 write!(f, "HEALTHCHECK")?;
-                unimplemented!();
+                match command {
+                    Some(command) => {
+                        if let Some(options) = options {
+                            write!(f, " {options}")?;
+                        }
+                        write!(f, " CMD")?;
+                        self.write_command(f, command)?;
+                    }
+                    None => write!(f, " NONE")?,
+                }
             }
             Instr::Label(inner) => {
                 assert!(!inner.is_empty());
@@ -166,46 +234,36 @@ impl<'a> Display for InstrDisplay<'a> {
                     write!(f, " {key}={}", escape_str(self.escape, value))?;
                 }
             }
-            Instr::Maintainer => {
-                write!(f, "MAINTAINER")?;
-                unimplemented!();
-            }
-            Instr::Onbuild => {
-                write!(f, "ONBUILD")?;
-                unimplemented!();
-            }
-            Instr::Run { command, options } => {
-                assert!(!command.is_empty());
+            Instr::Maintainer(inner) => write!(f, "MAINTAINER {inner}")?,
+            Instr::Onbuild(inner) => write!(f, "ONBUILD {}", inner.display(Some(self.escape)))?,
+            Instr::Run {
+                command,
+                heredocs,
+                options,
+            } => {
                 write!(f, "RUN")?;
                 if let Some(options) = options {
                     write!(f, " {options}")?;
                 }
-                let mut args = vec![];
-                for arg in command {
-                    args.push(escape_str(self.escape, arg));
-                }
-                write!(f, " [{}]", args.join(", "))?;
+                self.write_command(f, command)?;
+                write_heredocs(f, heredocs)?;
             }
-            Instr::Shell => {
-                write!(f, "SHELL")?;
-                unimplemented!();
-            }
-            Instr::Stopsignal => {
-                write!(f, "STOPSIGNAL")?;
-                unimplemented!();
-            }
-            Instr::User => {
-                write!(f, "USER")?;
-                unimplemented!();
+            Instr::Shell(inner) => {
+                assert!(!inner.is_empty());
+                write!(f, "SHELL {}", escape_array(self.escape, inner))?;
             }
-            Instr::Volume => {
-                write!(f, "VOLUME")?;
-                unimplemented!();
+            Instr::Stopsignal(inner) => write!(f, "STOPSIGNAL {inner}")?,
+            Instr::User { group, user } => {
+                write!(f, "USER {user}")?;
+                if let Some(group) = group {
+                    write!(f, ":{group}")?;
+                }
             }
-            Instr::Workdir => {
-                write!(f, "WORKDIR")?;
-                unimplemented!();
+            Instr::Volume(inner) => {
+                assert!(!inner.is_empty());
+                write!(f, "VOLUME {}", escape_array(self.escape, inner))?;
             }
+            Instr::Workdir(inner) => write!(f, "WORKDIR {}", inner.to_str().expect("UTF-8"))?,
         }
         Ok(())
     }
@@ -213,12 +271,24 @@ impl<'a> Display for InstrDisplay<'a> {
 
 pub const DEFAULT_ESCAPE: char = '\\';
 
+fn escape_array(escape: char, args: &[String]) -> String {
+    let args = args.iter().map(|arg| escape_str(escape, arg)).collect::<Vec<_>>();
+    format!("[ {} ]", args.join(", "))
+}
+
 fn escape_str(escape: char, s: &str) -> String {
     let double = format!("{escape}{escape}");
     let quote = format!("{escape}\"");
     format!("\"{}\"", s.replace(escape, &double).replace('"', &quote))
 }
 
+fn write_heredocs(f: &mut Formatter, heredocs: &[Heredoc]) -> std::fmt::Result {
+    for heredoc in heredocs {
+        write!(f, "\n{}{}", heredoc.content, heredoc.delimiter)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +308,7 @@ mod tests {
     fn instr_copy() {
         let instr = Instr::Copy {
             destination: PathBuf::from("/app/baz/"),
+            heredocs: vec![],
             options: Some(CopyOptions {
                 link: true,
                 ..Default::default()
@@ -280,7 +351,8 @@ mod tests {
     #[test]
     fn instr_run() {
         let instr = Instr::Run {
-            command: vec![String::from("foo"), String::from("--bar=42")],
+            command: Command::Exec(vec![String::from("foo"), String::from("--bar=42")]),
+            heredocs: vec![],
             options: Some(RunOptions {
                 mount: Some(vec![Mount::Tmpfs {
                     destination: PathBuf::from("/tmp/"),
@@ -1,7 +1,10 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
-#[derive(Debug)]
+use crate::error::Error;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Device {
     pub name: String,
     pub options: Option<DeviceOptions>,
@@ -17,12 +20,35 @@ impl Display for Device {
             args.push(format!("{options}"));
         }
 
-        write!(f, "{}", args.join(","))?;
+        write!(f, "--device={}", args.join(","))?;
         Ok(())
     }
 }
 
-#[derive(Debug, Default)]
+impl FromStr for Device {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(',');
+        let name = fields.next().unwrap_or_default().to_string();
+        let mut options = None;
+        for field in fields {
+            match field {
+                "required" | "required=true" => options = Some(DeviceOptions { required: true }),
+                "required=false" => options = Some(DeviceOptions { required: false }),
+                _ => {
+                    return Err(Error::FlagInvalid {
+                        flag: String::from("device"),
+                        value: s.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(Self { name, options })
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct DeviceOptions {
     pub required: bool,
 }
@@ -36,7 +62,7 @@ impl Display for DeviceOptions {
             args.push(String::from("required"));
         }
 
-        write!(f, "--device={}", args.join(","))?;
+        write!(f, "{}", args.join(","))?;
         Ok(())
     }
 }
@@ -11,7 +11,7 @@ use crate::instr::run::mount::Mount;
 use crate::instr::run::network::Network;
 use crate::instr::run::security::Security;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct RunOptions {
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run---device
     pub device: Option<Vec<Device>>,
@@ -2,17 +2,33 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::path::PathBuf;
 
-use crate::instr::DEFAULT_ESCAPE;
-use crate::instr::escape_str;
+use crate::error::Error;
+use crate::error::Result;
 use crate::instr::from::FromKind;
+use crate::instr::run::mount::csv_field;
+use crate::instr::run::mount::parse_bool;
+use crate::instr::run::mount::parse_str;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct BindOptions {
     pub from: Option<FromKind>,
     pub readwrite: bool,
     pub source: Option<PathBuf>,
 }
 
+impl BindOptions {
+    pub(crate) fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "from" => self.from = Some(parse_str(key, value)?.parse().expect("infallible")),
+            "readonly" | "ro" => self.readwrite = !parse_bool(key, value)?,
+            "readwrite" | "rw" => self.readwrite = parse_bool(key, value)?,
+            "source" | "src" => self.source = Some(PathBuf::from(parse_str(key, value)?)),
+            _ => return Err(Error::MountInvalid { key: key.to_string() }),
+        }
+        Ok(())
+    }
+}
+
 impl Display for BindOptions {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let Self {
@@ -23,17 +39,13 @@ impl Display for BindOptions {
         let mut args = vec![];
 
         if let Some(from) = from {
-            args.push(format!("from={from}"));
+            args.push(csv_field(format!("from={from}")));
         }
         if *readwrite {
             args.push(String::from("readwrite"));
         }
         if let Some(source) = source {
-            args.push(format!(
-                "source={}",
-                // TODO: handle escape char
-                escape_str(DEFAULT_ESCAPE, source.to_str().expect("UTF-8"))
-            ));
+            args.push(csv_field(format!("source={}", source.to_str().expect("UTF-8"))));
         }
 
         write!(f, "{}", args.join(","))?;
@@ -1,12 +1,17 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use crate::instr::DEFAULT_ESCAPE;
-use crate::instr::escape_str;
+use crate::error::Error;
+use crate::error::Result;
 use crate::instr::from::FromKind;
+use crate::instr::run::mount::csv_field;
+use crate::instr::run::mount::parse_bool;
+use crate::instr::run::mount::parse_str;
+use crate::instr::run::mount::parse_u64;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct CacheOptions {
     pub from: Option<FromKind>,
     pub gid: Option<u64>,
@@ -18,6 +23,24 @@ pub struct CacheOptions {
     pub uid: Option<u64>,
 }
 
+impl CacheOptions {
+    pub(crate) fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "from" => self.from = Some(parse_str(key, value)?.parse().expect("infallible")),
+            "gid" => self.gid = Some(parse_u64(key, value, 10)?),
+            "id" => self.id = Some(parse_str(key, value)?),
+            "mode" => self.mode = Some(parse_u64(key, value, 8)?),
+            "readonly" | "ro" => self.readonly = parse_bool(key, value)?,
+            "readwrite" | "rw" => self.readonly = !parse_bool(key, value)?,
+            "sharing" => self.sharing = Some(parse_str(key, value)?.parse()?),
+            "source" | "src" => self.source = Some(PathBuf::from(parse_str(key, value)?)),
+            "uid" => self.uid = Some(parse_u64(key, value, 10)?),
+            _ => return Err(Error::MountInvalid { key: key.to_string() }),
+        }
+        Ok(())
+    }
+}
+
 impl Display for CacheOptions {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let Self {
@@ -33,16 +56,16 @@ impl Display for CacheOptions {
         let mut args = vec![];
 
         if let Some(from) = from {
-            args.push(format!("from={from}"));
+            args.push(csv_field(format!("from={from}")));
         }
-        if let Some(_gid) = gid {
-            unimplemented!();
+        if let Some(gid) = gid {
+            args.push(format!("gid={gid}"));
         }
         if let Some(id) = id {
-            args.push(format!("id={id}"));
+            args.push(csv_field(format!("id={id}")));
         }
-        if let Some(_mode) = mode {
-            unimplemented!();
+        if let Some(mode) = mode {
+            args.push(format!("mode={mode:04o}"));
         }
         if *readonly {
             args.push(String::from("readonly"));
@@ -51,14 +74,10 @@ impl Display for CacheOptions {
             args.push(format!("sharing={sharing}"));
         }
         if let Some(source) = source {
-            // TODO: handle escape char
-            args.push(format!(
-                "source={}",
-                escape_str(DEFAULT_ESCAPE, source.to_str().expect("UTF-8"))
-            ));
+            args.push(csv_field(format!("source={}", source.to_str().expect("UTF-8"))));
         }
-        if let Some(_uid) = uid {
-            unimplemented!();
+        if let Some(uid) = uid {
+            args.push(format!("uid={uid}"));
         }
 
         write!(f, "{}", args.join(","))?;
@@ -66,7 +85,7 @@ impl Display for CacheOptions {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum Sharing {
     Locked,
     Private,
@@ -84,3 +103,18 @@ impl Display for Sharing {
         Ok(())
     }
 }
+
+impl FromStr for Sharing {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "locked" => Ok(Sharing::Locked),
+            "private" => Ok(Sharing::Private),
+            "shared" => Ok(Sharing::Shared),
+            _ => Err(Error::MountInvalid {
+                key: format!("sharing={s}"),
+            }),
+        }
+    }
+}
@@ -7,16 +7,17 @@ pub mod tmpfs;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use crate::instr::DEFAULT_ESCAPE;
-use crate::instr::escape_str;
+use crate::error::Error;
+use crate::error::Result;
 use crate::instr::run::mount::bind::BindOptions;
 use crate::instr::run::mount::cache::CacheOptions;
 use crate::instr::run::mount::secret::SecretOptions;
 use crate::instr::run::mount::ssh::SshOptions;
 use crate::instr::run::mount::tmpfs::TmpfsOptions;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Mount {
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run---mounttypebind
     Bind {
@@ -29,12 +30,9 @@ pub enum Mount {
         options: Option<CacheOptions>,
     },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run---mounttypesecret
-    Secret {
-        options: Option<SecretOptions>,
-    },
-    Ssh {
-        options: Option<SshOptions>,
-    },
+    Secret { options: Option<SecretOptions> },
+    ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run---mounttypessh
+    Ssh { options: Option<SshOptions> },
     ///https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#run---mounttypetmpfs
     Tmpfs {
         destination: PathBuf,
@@ -49,22 +47,20 @@ impl Display for Mount {
         match self {
             Mount::Bind { destination, options } => {
                 args.push(String::from("type=bind"));
-                // TODO: handle escape char
-                args.push(format!(
+                args.push(csv_field(format!(
                     "destination={}",
-                    escape_str(DEFAULT_ESCAPE, destination.to_str().expect("UTF-8"))
-                ));
+                    destination.to_str().expect("UTF-8")
+                )));
                 if let Some(options) = options {
                     args.push(format!("{options}"));
                 }
             }
             Mount::Cache { destination, options } => {
                 args.push(String::from("type=cache"));
-                // TODO: handle escape char
-                args.push(format!(
+                args.push(csv_field(format!(
                     "destination={}",
-                    escape_str(DEFAULT_ESCAPE, destination.to_str().expect("UTF-8"))
-                ));
+                    destination.to_str().expect("UTF-8")
+                )));
                 if let Some(options) = options {
                     args.push(format!("{options}"));
                 }
@@ -83,18 +79,135 @@ impl Display for Mount {
             }
             Mount::Tmpfs { destination, options } => {
                 args.push(String::from("type=tmpfs"));
-                // TODO: handle escape char
-                args.push(format!(
+                args.push(csv_field(format!(
                     "destination={}",
-                    escape_str(DEFAULT_ESCAPE, destination.to_str().expect("UTF-8"))
-                ));
+                    destination.to_str().expect("UTF-8")
+                )));
                 if let Some(options) = options {
                     args.push(format!("{options}"));
                 }
             }
         }
 
+        args.retain(|arg| !arg.is_empty());
         write!(f, "--mount={}", args.join(","))?;
         Ok(())
     }
 }
+
+impl FromStr for Mount {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut kind = None;
+        let mut fields = vec![];
+        for field in csv_split(s) {
+            let (key, value) = match field.split_once('=') {
+                Some((key, value)) => (key.to_lowercase(), Some(value.to_string())),
+                None => (field.to_lowercase(), None),
+            };
+            match key.as_str() {
+                "type" => kind = value,
+                _ => fields.push((key, value)),
+            }
+        }
+
+        fn options<T: Default + PartialEq>(
+            fields: &[(String, Option<String>)],
+            mut set: impl FnMut(&mut T, &str, Option<&str>) -> Result<()>,
+        ) -> Result<Option<T>> {
+            let mut options = T::default();
+            for (key, value) in fields {
+                set(&mut options, key, value.as_deref())?;
+            }
+            Ok((options != T::default()).then_some(options))
+        }
+
+        fn destination(fields: &mut Vec<(String, Option<String>)>) -> Result<PathBuf> {
+            let index = fields
+                .iter()
+                .position(|(key, _)| matches!(key.as_str(), "destination" | "dst" | "target"));
+            match index.map(|index| fields.remove(index)) {
+                Some((_, Some(value))) => Ok(PathBuf::from(value)),
+                _ => Err(Error::MountInvalid {
+                    key: String::from("destination"),
+                }),
+            }
+        }
+
+        Ok(match kind.as_deref().unwrap_or("bind") {
+            "bind" => Mount::Bind {
+                destination: destination(&mut fields)?,
+                options: options(&fields, BindOptions::set)?,
+            },
+            "cache" => Mount::Cache {
+                destination: destination(&mut fields)?,
+                options: options(&fields, CacheOptions::set)?,
+            },
+            "secret" => Mount::Secret {
+                options: options(&fields, SecretOptions::set)?,
+            },
+            "ssh" => Mount::Ssh {
+                options: options(&fields, SshOptions::set)?,
+            },
+            "tmpfs" => Mount::Tmpfs {
+                destination: destination(&mut fields)?,
+                options: options(&fields, TmpfsOptions::set)?,
+            },
+            other => {
+                return Err(Error::MountInvalid {
+                    key: format!("type={other}"),
+                });
+            }
+        })
+    }
+}
+
+/// Quotes a `key=value` field the way `encoding/csv` expects it
+pub(crate) fn csv_field(field: String) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+pub(crate) fn csv_split(s: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+pub(crate) fn parse_bool(key: &str, value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(_) => Err(Error::MountInvalid { key: key.to_string() }),
+    }
+}
+
+pub(crate) fn parse_u64(key: &str, value: Option<&str>, radix: u32) -> Result<u64> {
+    value
+        .and_then(|value| u64::from_str_radix(value, radix).ok())
+        .ok_or_else(|| Error::MountInvalid { key: key.to_string() })
+}
+
+pub(crate) fn parse_str(key: &str, value: Option<&str>) -> Result<String> {
+    value
+        .map(str::to_string)
+        .ok_or_else(|| Error::MountInvalid { key: key.to_string() })
+}
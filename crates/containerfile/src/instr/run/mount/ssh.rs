@@ -2,10 +2,14 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::path::PathBuf;
 
-use crate::instr::DEFAULT_ESCAPE;
-use crate::instr::escape_str;
+use crate::error::Error;
+use crate::error::Result;
+use crate::instr::run::mount::csv_field;
+use crate::instr::run::mount::parse_bool;
+use crate::instr::run::mount::parse_str;
+use crate::instr::run::mount::parse_u64;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct SshOptions {
     pub destination: Option<PathBuf>,
     pub gid: Option<u64>,
@@ -15,6 +19,21 @@ pub struct SshOptions {
     pub uid: Option<u64>,
 }
 
+impl SshOptions {
+    pub(crate) fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "destination" | "dst" | "target" => self.destination = Some(PathBuf::from(parse_str(key, value)?)),
+            "gid" => self.gid = Some(parse_u64(key, value, 10)?),
+            "id" => self.id = Some(parse_str(key, value)?),
+            "mode" => self.mode = Some(parse_u64(key, value, 8)?),
+            "required" => self.required = parse_bool(key, value)?,
+            "uid" => self.uid = Some(parse_u64(key, value, 10)?),
+            _ => return Err(Error::MountInvalid { key: key.to_string() }),
+        }
+        Ok(())
+    }
+}
+
 impl Display for SshOptions {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let Self {
@@ -28,26 +47,25 @@ impl Display for SshOptions {
         let mut args = vec![];
 
         if let Some(destination) = destination {
-            // TODO: handle escape char
-            args.push(format!(
+            args.push(csv_field(format!(
                 "destination={}",
-                escape_str(DEFAULT_ESCAPE, destination.to_str().expect("UTF-8"))
-            ));
+                destination.to_str().expect("UTF-8")
+            )));
         }
-        if let Some(_gid) = gid {
-            unimplemented!();
+        if let Some(gid) = gid {
+            args.push(format!("gid={gid}"));
         }
         if let Some(id) = id {
-            args.push(format!("id={id}"));
+            args.push(csv_field(format!("id={id}")));
         }
-        if let Some(_mode) = mode {
-            unimplemented!();
+        if let Some(mode) = mode {
+            args.push(format!("mode={mode:04o}"));
         }
         if *required {
             args.push(String::from("required"));
         }
-        if let Some(_uid) = uid {
-            unimplemented!();
+        if let Some(uid) = uid {
+            args.push(format!("uid={uid}"));
         }
 
         write!(f, "{}", args.join(","))?;
@@ -1,18 +1,33 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
 
-#[derive(Debug, Default)]
+use crate::error::Error;
+use crate::error::Result;
+use crate::instr::run::mount::csv_field;
+use crate::instr::run::mount::parse_str;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct TmpfsOptions {
     pub size: Option<String>,
 }
 
+impl TmpfsOptions {
+    pub(crate) fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "size" => self.size = Some(parse_str(key, value)?),
+            _ => return Err(Error::MountInvalid { key: key.to_string() }),
+        }
+        Ok(())
+    }
+}
+
 impl Display for TmpfsOptions {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let Self { size } = self;
         let mut args = vec![];
 
         if let Some(size) = size {
-            args.push(size.clone());
+            args.push(csv_field(format!("size={size}")));
         }
 
         write!(f, "{}", args.join(","))?;
@@ -1,7 +1,10 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
-#[derive(Debug, Default)]
+use crate::error::Error;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum Network {
     #[default]
     Default,
@@ -13,10 +16,26 @@ impl Display for Network {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "--network=")?;
         match self {
-            Network::Default => { /* write!(f, "default")?, */ }
+            Network::Default => write!(f, "default")?,
             Network::Host => write!(f, "host")?,
             Network::None => write!(f, "none")?,
         }
         Ok(())
     }
 }
+
+impl FromStr for Network {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Network::Default),
+            "host" => Ok(Network::Host),
+            "none" => Ok(Network::None),
+            _ => Err(Error::FlagInvalid {
+                flag: String::from("network"),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
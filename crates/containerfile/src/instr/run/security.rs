@@ -1,7 +1,10 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
-#[derive(Debug, Default)]
+use crate::error::Error;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum Security {
     Insecure,
     #[default]
@@ -13,8 +16,23 @@ impl Display for Security {
         write!(f, "--security=")?;
         match self {
             Security::Insecure => write!(f, "insecure")?,
-            Security::Sandbox => { /* write!(f, "sandbox")?, */ }
+            Security::Sandbox => write!(f, "sandbox")?,
         }
         Ok(())
     }
 }
+
+impl FromStr for Security {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insecure" => Ok(Security::Insecure),
+            "sandbox" => Ok(Security::Sandbox),
+            _ => Err(Error::FlagInvalid {
+                flag: String::from("security"),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
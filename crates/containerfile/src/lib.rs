@@ -1,12 +1,18 @@
+pub mod error;
 pub mod instr;
+mod parser;
 
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::str::FromStr;
 
+use crate::error::Error;
 use crate::instr::Instr;
 use crate::instr::directive::Directive;
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Containerfile(Vec<Instr>);
 
 impl Containerfile {
@@ -19,6 +25,20 @@ impl Containerfile {
     }
 }
 
+impl Deref for Containerfile {
+    type Target = Vec<Instr>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Containerfile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 impl Display for Containerfile {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         assert!(!self.0.is_empty());
@@ -36,6 +56,14 @@ impl Display for Containerfile {
     }
 }
 
+impl FromStr for Containerfile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::parse(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
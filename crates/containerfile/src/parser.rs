@@ -0,0 +1,875 @@
+use std::path::PathBuf;
+
+use crate::Containerfile;
+use crate::error::Error;
+use crate::error::Result;
+use crate::instr::DEFAULT_ESCAPE;
+use crate::instr::Instr;
+use crate::instr::add::AddOptions;
+use crate::instr::command::Command;
+use crate::instr::copy::CopyOptions;
+use crate::instr::directive::Directive;
+use crate::instr::from::FromKind;
+use crate::instr::healthcheck::HealthcheckOptions;
+use crate::instr::heredoc::Heredoc;
+use crate::instr::run::RunOptions;
+use crate::instr::run::mount::Mount;
+
+type Flags = Vec<(String, Option<String>)>;
+
+/// @see: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/parser/parser.go
+pub(crate) fn parse(s: &str) -> Result<Containerfile> {
+    let mut parser = Parser {
+        escape: DEFAULT_ESCAPE,
+        index: 0,
+        lines: s.strip_prefix('\u{feff}').unwrap_or(s).lines().collect(),
+        stages: vec![],
+    };
+    let mut cf = Containerfile::default();
+    let mut directives = true;
+    while let Some(line) = parser.next_line() {
+        let line_number = parser.index;
+        let instr = parser
+            .parse_line(line, &mut directives)
+            .map_err(|source| Error::Syntax {
+                line: line_number,
+                source: Box::new(source),
+            })?;
+        cf.push(instr);
+    }
+    Ok(cf)
+}
+
+struct Parser<'a> {
+    escape: char,
+    index: usize,
+    lines: Vec<&'a str>,
+    /// Lowercase names of the stages declared so far, used to tell `--from=<stage>` apart from images
+    stages: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn next_line(&mut self) -> Option<&'a str> {
+        let line = self.lines.get(self.index).copied();
+        if line.is_some() {
+            self.index += 1;
+        }
+        line
+    }
+
+    fn parse_line(&mut self, line: &'a str, directives: &mut bool) -> Result<Instr> {
+        if *directives {
+            match parse_directive(line)? {
+                Some(directive) => {
+                    if let Directive::Escape(c) = directive {
+                        self.escape = c;
+                    }
+                    return Ok(Instr::Directive(directive));
+                }
+                None => *directives = false,
+            }
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(Instr::Empty);
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            return Ok(Instr::Comment(comment.strip_prefix(' ').unwrap_or(comment).to_string()));
+        }
+
+        let logical = self.logical_line(line);
+        let logical = logical.trim();
+        let (keyword, rest) = logical.split_once(char::is_whitespace).unwrap_or((logical, ""));
+        self.parse_instr(keyword, rest.trim())
+    }
+
+    /// Joins lines ending with the escape character, skipping comments and blank lines in between
+    fn logical_line(&mut self, first: &str) -> String {
+        let mut logical = String::new();
+        let mut line = first;
+        loop {
+            let Some(content) = line.trim_end().strip_suffix(self.escape) else {
+                logical.push_str(line);
+                return logical;
+            };
+            logical.push_str(content);
+            loop {
+                match self.next_line() {
+                    None => return logical,
+                    Some(next) if next.trim().is_empty() || next.trim_start().starts_with('#') => continue,
+                    Some(next) => {
+                        line = next;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn parse_instr(&mut self, keyword: &str, rest: &str) -> Result<Instr> {
+        let instr = keyword.to_uppercase();
+        let (flags, rest) = split_flags(rest);
+        match instr.as_str() {
+            "ADD" => {
+                let (source, destination, heredocs) = self.parse_paths(&instr, rest)?;
+                let mut options = AddOptions::default();
+                for (flag, value) in flags {
+                    match flag.as_str() {
+                        "checksum" => options.checksum = Some(flag_value(&flag, value)?),
+                        "chmod" => options.chmod = Some(flag_value(&flag, value)?),
+                        "chown" => options.chown = Some(flag_value(&flag, value)?),
+                        "exclude" => options.exclude.get_or_insert_default().push(flag_value(&flag, value)?),
+                        "keep-git-dir" => options.keep_git_dir = flag_bool(&flag, value)?,
+                        "link" => options.link = flag_bool(&flag, value)?,
+                        _ => return Err(Error::FlagUnknown { flag, instr }),
+                    }
+                }
+                Ok(Instr::Add {
+                    destination,
+                    heredocs,
+                    options: (options != AddOptions::default()).then_some(options),
+                    source,
+                })
+            }
+            "ARG" => {
+                no_flags(&instr, flags)?;
+                let mut args = vec![];
+                for word in split_words(rest, self.escape) {
+                    match split_key_value(word) {
+                        Some((name, value)) => args.push((name.to_string(), Some(unquote(value, self.escape)))),
+                        None => args.push((word.to_string(), None)),
+                    }
+                }
+                min_args(&instr, args.len(), 1)?;
+                Ok(Instr::Arg(args))
+            }
+            "CMD" => {
+                no_flags(&instr, flags)?;
+                Ok(Instr::Cmd(self.parse_command(&instr, rest)?))
+            }
+            "COPY" => {
+                let (source, destination, heredocs) = self.parse_paths(&instr, rest)?;
+                let mut options = CopyOptions::default();
+                for (flag, value) in flags {
+                    match flag.as_str() {
+                        "chmod" => options.chmod = Some(flag_value(&flag, value)?),
+                        "chown" => options.chown = Some(flag_value(&flag, value)?),
+                        "exclude" => options.exclude.get_or_insert_default().push(flag_value(&flag, value)?),
+                        "from" => options.from = Some(self.resolve_from(&flag_value(&flag, value)?)),
+                        "link" => options.link = flag_bool(&flag, value)?,
+                        "parents" => options.parents = flag_bool(&flag, value)?,
+                        _ => return Err(Error::FlagUnknown { flag, instr }),
+                    }
+                }
+                Ok(Instr::Copy {
+                    destination,
+                    heredocs,
+                    options: (options != CopyOptions::default()).then_some(options),
+                    source,
+                })
+            }
+            "ENTRYPOINT" => {
+                no_flags(&instr, flags)?;
+                Ok(Instr::Entrypoint(self.parse_command(&instr, rest)?))
+            }
+            "ENV" => {
+                no_flags(&instr, flags)?;
+                Ok(Instr::Env(self.parse_key_values(&instr, rest)?))
+            }
+            "EXPOSE" => {
+                no_flags(&instr, flags)?;
+                let ports = split_words(rest, self.escape)
+                    .into_iter()
+                    .map(|word| unquote(word, self.escape))
+                    .collect::<Vec<_>>();
+                min_args(&instr, ports.len(), 1)?;
+                Ok(Instr::Expose(ports))
+            }
+            "FROM" => {
+                let mut platform = None;
+                for (flag, value) in flags {
+                    match flag.as_str() {
+                        "platform" => platform = Some(flag_value(&flag, value)?),
+                        _ => return Err(Error::FlagUnknown { flag, instr }),
+                    }
+                }
+                let words = split_words(rest, self.escape);
+                let (image, name) = match words.as_slice() {
+                    [image] => (*image, None),
+                    [image, r#as, name] if r#as.eq_ignore_ascii_case("AS") => (*image, Some(name.to_string())),
+                    _ => return Err(Error::InstrArgsMissing { instr, min: 1 }),
+                };
+                let kind = self.resolve_from(image);
+                if let Some(name) = &name {
+                    self.stages.push(name.to_lowercase());
+                }
+                Ok(Instr::From { kind, name, platform })
+            }
+            "HEALTHCHECK" => {
+                let mut options = HealthcheckOptions::default();
+                for (flag, value) in flags {
+                    match flag.as_str() {
+                        "interval" => options.interval = Some(flag_value(&flag, value)?),
+                        "retries" => {
+                            let value = flag_value(&flag, value)?;
+                            options.retries = Some(value.parse().map_err(|_| Error::FlagInvalid { flag, value })?);
+                        }
+                        "start-interval" => options.start_interval = Some(flag_value(&flag, value)?),
+                        "start-period" => options.start_period = Some(flag_value(&flag, value)?),
+                        "timeout" => options.timeout = Some(flag_value(&flag, value)?),
+                        _ => return Err(Error::FlagUnknown { flag, instr }),
+                    }
+                }
+                let (keyword, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let command = match keyword.to_uppercase().as_str() {
+                    "CMD" => Some(self.parse_command(&instr, rest.trim())?),
+                    "NONE" => None,
+                    _ => return Err(Error::InstrArgsMissing { instr, min: 1 }),
+                };
+                Ok(Instr::Healthcheck {
+                    command,
+                    options: (options != HealthcheckOptions::default()).then_some(options),
+                })
+            }
+            "LABEL" => {
+                no_flags(&instr, flags)?;
+                Ok(Instr::Label(self.parse_key_values(&instr, rest)?))
+            }
+            "MAINTAINER" => {
+                no_flags(&instr, flags)?;
+                min_args(&instr, rest.len(), 1)?;
+                Ok(Instr::Maintainer(rest.to_string()))
+            }
+            "ONBUILD" => {
+                no_flags(&instr, flags)?;
+                let (keyword, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                min_args(&instr, keyword.len(), 1)?;
+                Ok(Instr::Onbuild(Box::new(self.parse_instr(keyword, rest.trim())?)))
+            }
+            "RUN" => {
+                let mut options = RunOptions::default();
+                for (flag, value) in flags {
+                    match flag.as_str() {
+                        "device" => options
+                            .device
+                            .get_or_insert_default()
+                            .push(flag_value(&flag, value)?.parse()?),
+                        "mount" => {
+                            let mut mount = flag_value(&flag, value)?.parse()?;
+                            self.resolve_mount(&mut mount);
+                            options.mount.get_or_insert_default().push(mount);
+                        }
+                        "network" => options.network = Some(flag_value(&flag, value)?.parse()?),
+                        "security" => options.security = Some(flag_value(&flag, value)?.parse()?),
+                        _ => return Err(Error::FlagUnknown { flag, instr }),
+                    }
+                }
+                let command = self.parse_command(&instr, rest)?;
+                let heredocs = match &command {
+                    Command::Exec(_) => vec![],
+                    Command::Shell(script) => self.parse_heredocs(script)?,
+                };
+                Ok(Instr::Run {
+                    command,
+                    heredocs,
+                    options: (options != RunOptions::default()).then_some(options),
+                })
+            }
+            "SHELL" => {
+                no_flags(&instr, flags)?;
+                match parse_json_array(rest, self.escape) {
+                    Some(args) if !args.is_empty() => Ok(Instr::Shell(args)),
+                    _ => Err(Error::InstrJsonInvalid { instr }),
+                }
+            }
+            "STOPSIGNAL" => {
+                no_flags(&instr, flags)?;
+                min_args(&instr, rest.len(), 1)?;
+                Ok(Instr::Stopsignal(rest.to_string()))
+            }
+            "USER" => {
+                no_flags(&instr, flags)?;
+                min_args(&instr, rest.len(), 1)?;
+                let (user, group) = match rest.split_once(':') {
+                    Some((user, group)) => (user.to_string(), Some(group.to_string())),
+                    None => (rest.to_string(), None),
+                };
+                Ok(Instr::User { group, user })
+            }
+            "VOLUME" => {
+                no_flags(&instr, flags)?;
+                let volumes = match parse_json_array(rest, self.escape) {
+                    Some(volumes) => volumes,
+                    None => split_words(rest, self.escape)
+                        .into_iter()
+                        .map(|word| unquote(word, self.escape))
+                        .collect(),
+                };
+                min_args(&instr, volumes.len(), 1)?;
+                Ok(Instr::Volume(volumes))
+            }
+            "WORKDIR" => {
+                no_flags(&instr, flags)?;
+                min_args(&instr, rest.len(), 1)?;
+                Ok(Instr::Workdir(PathBuf::from(unquote(rest, self.escape))))
+            }
+            _ => Err(Error::InstrUnknown { instr }),
+        }
+    }
+
+    fn parse_command(&self, instr: &str, rest: &str) -> Result<Command> {
+        min_args(instr, rest.len(), 1)?;
+        Ok(match parse_json_array(rest, self.escape) {
+            Some(args) => Command::Exec(args),
+            None => Command::Shell(rest.to_string()),
+        })
+    }
+
+    /// @see: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#here-documents
+    fn parse_heredocs(&mut self, s: &str) -> Result<Vec<Heredoc>> {
+        let mut heredocs = vec![];
+        for (chomp, delimiter, expand) in find_heredocs(s, self.escape) {
+            let mut content = String::new();
+            loop {
+                let Some(line) = self.next_line() else {
+                    return Err(Error::HeredocUnterminated { delimiter });
+                };
+                let line_cmp = if chomp { line.trim_start_matches('\t') } else { line };
+                if line_cmp == delimiter {
+                    break;
+                }
+                content.push_str(line);
+                content.push('\n');
+            }
+            heredocs.push(Heredoc {
+                chomp,
+                content,
+                delimiter,
+                expand,
+            });
+        }
+        Ok(heredocs)
+    }
+
+    fn parse_key_values(&self, instr: &str, rest: &str) -> Result<Vec<(String, String)>> {
+        let words = split_words(rest, self.escape);
+        min_args(instr, words.len(), 1)?;
+
+        // legacy `ENV key value` form
+        if split_key_value(words[0]).is_none() {
+            let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            min_args(instr, value.trim().len(), 1)?;
+            return Ok(vec![(unquote(key, self.escape), unquote(value.trim(), self.escape))]);
+        }
+
+        let mut pairs = vec![];
+        for word in words {
+            let (key, value) = split_key_value(word).ok_or_else(|| Error::InstrArgsMissing {
+                instr: instr.to_string(),
+                min: 1,
+            })?;
+            pairs.push((unquote(key, self.escape), unquote(value, self.escape)));
+        }
+        Ok(pairs)
+    }
+
+    fn parse_paths(&mut self, instr: &str, rest: &str) -> Result<(Vec<PathBuf>, PathBuf, Vec<Heredoc>)> {
+        let (mut paths, heredocs) = match parse_json_array(rest, self.escape) {
+            Some(paths) => (paths, vec![]),
+            None => {
+                let paths = split_words(rest, self.escape)
+                    .into_iter()
+                    .map(|word| unquote(word, self.escape))
+                    .collect();
+                (paths, self.parse_heredocs(rest)?)
+            }
+        };
+        min_args(instr, paths.len(), 2)?;
+        let destination = PathBuf::from(paths.pop().expect("destination exists"));
+        Ok((paths.into_iter().map(PathBuf::from).collect(), destination, heredocs))
+    }
+
+    fn resolve_from(&self, s: &str) -> FromKind {
+        if self.stages.contains(&s.to_lowercase()) || s.parse::<usize>().is_ok() {
+            return FromKind::Stage(s.to_string());
+        }
+        s.parse().expect("infallible")
+    }
+
+    fn resolve_mount(&self, mount: &mut Mount) {
+        let from = match mount {
+            Mount::Bind { options, .. } => options.as_mut().and_then(|options| options.from.as_mut()),
+            Mount::Cache { options, .. } => options.as_mut().and_then(|options| options.from.as_mut()),
+            _ => None,
+        };
+        if let Some(from) = from {
+            *from = self.resolve_from(&from.to_string());
+        }
+    }
+}
+
+fn parse_directive(line: &str) -> Result<Option<Directive>> {
+    let Some((key, value)) = line.trim().strip_prefix('#').and_then(|rest| rest.split_once('=')) else {
+        return Ok(None);
+    };
+    let (key, value) = (key.trim(), value.trim());
+    Ok(match key.to_lowercase().as_str() {
+        "check" => Some(Directive::Check(value.to_string())),
+        "escape" => match value {
+            "\\" | "`" => Some(Directive::Escape(value.chars().next().expect("escape exists"))),
+            _ => return Err(Error::EscapeInvalid { got: value.to_string() }),
+        },
+        "syntax" => Some(Directive::Syntax(value.to_string())),
+        _ => None,
+    })
+}
+
+/// Heredocs opened by the shell form, i.e. the words starting with `<<` outside of `$((…))` arithmetic
+///
+/// @see: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/parser/parser.go
+fn find_heredocs(s: &str, escape: char) -> Vec<(bool, String, bool)> {
+    let mut heredocs = vec![];
+    let mut depth = 0usize;
+    for word in split_words(s, escape) {
+        if depth == 0 {
+            heredocs.extend(parse_heredoc_word(word));
+        }
+        let mut rest = word;
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("$((") {
+                depth += 2;
+                rest = &rest[3..];
+                continue;
+            }
+            match c {
+                '(' if depth > 0 => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    heredocs
+}
+
+/// `<<EOF`, `<<-EOF` or `<<'EOF'`, as chomp, delimiter and whether the content is expanded
+fn parse_heredoc_word(word: &str) -> Option<(bool, String, bool)> {
+    let rest = word.strip_prefix("<<")?;
+    if rest.starts_with('<') {
+        return None;
+    }
+    let (chomp, rest) = match rest.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''));
+    let rest = match quote {
+        Some(quote) => &rest[quote.len_utf8()..],
+        None => rest,
+    };
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if end == 0 {
+        return None;
+    }
+    if let Some(quote) = quote {
+        rest[end..].strip_prefix(quote)?;
+    }
+    Some((chomp, rest[..end].to_string(), quote.is_none()))
+}
+
+fn flag_bool(flag: &str, value: Option<String>) -> Result<bool> {
+    match value.as_deref() {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(value) => Err(Error::FlagInvalid {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String> {
+    value.ok_or_else(|| Error::FlagInvalid {
+        flag: flag.to_string(),
+        value: String::new(),
+    })
+}
+
+fn min_args(instr: &str, got: usize, min: usize) -> Result<()> {
+    if got < min {
+        return Err(Error::InstrArgsMissing {
+            instr: instr.to_string(),
+            min,
+        });
+    }
+    Ok(())
+}
+
+fn no_flags(instr: &str, flags: Flags) -> Result<()> {
+    match flags.into_iter().next() {
+        Some((flag, _)) => Err(Error::FlagUnknown {
+            flag,
+            instr: instr.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Mirrors `escape_str`, JSON escapes are only recognized with the default escape character
+fn parse_json_array(s: &str, escape: char) -> Option<Vec<String>> {
+    let inner = s.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut args = vec![];
+    let mut chars = inner.trim().chars().peekable();
+    if chars.peek().is_none() {
+        return Some(args);
+    }
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next()? != '"' {
+            return None;
+        }
+        let mut arg = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                c if c == escape => {
+                    let c = chars.next()?;
+                    match (escape, c) {
+                        ('\\', 'b') => arg.push('\u{8}'),
+                        ('\\', 'f') => arg.push('\u{c}'),
+                        ('\\', 'n') => arg.push('\n'),
+                        ('\\', 'r') => arg.push('\r'),
+                        ('\\', 't') => arg.push('\t'),
+                        ('\\', 'u') => {
+                            let hex = (0..4).map(|_| chars.next()).collect::<Option<String>>()?;
+                            arg.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                        }
+                        (_, c) => arg.push(c),
+                    }
+                }
+                c => arg.push(c),
+            }
+        }
+        args.push(arg);
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            Some(',') => continue,
+            None => return Some(args),
+            Some(_) => return None,
+        }
+    }
+}
+
+/// Splits leading `--flag[=value]` words off the arguments
+fn split_flags(s: &str) -> (Flags, &str) {
+    let mut flags = vec![];
+    let mut rest = s.trim_start();
+    while let Some(stripped) = rest.strip_prefix("--") {
+        let end = word_end(stripped, None);
+        let word = &stripped[..end];
+        flags.push(match word.split_once('=') {
+            Some((flag, value)) => (flag.to_lowercase(), Some(value.to_string())),
+            None => (word.to_lowercase(), None),
+        });
+        rest = stripped[end..].trim_start();
+    }
+    (flags, rest)
+}
+
+fn split_key_value(word: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (index, c) in word.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '=') => return Some((&word[..index], &word[index + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn split_words(s: &str, escape: char) -> Vec<&str> {
+    let mut words = vec![];
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let end = word_end(rest, Some(escape));
+        words.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    words
+}
+
+/// @see: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/shell/lex.go
+fn unquote(s: &str, escape: char) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => out.extend(chars.by_ref().take_while(|c| *c != '\'')),
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        c if c == escape => match chars.next() {
+                            Some(next) if next == '"' || next == '$' || next == escape => out.push(next),
+                            Some(next) => out.extend([c, next]),
+                            None => out.push(c),
+                        },
+                        c => out.push(c),
+                    }
+                }
+            }
+            c if c == escape => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Byte offset of the first whitespace that is neither quoted nor escaped
+fn word_end(s: &str, escape: Option<char>) -> usize {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (_, c) if Some(c) == escape && quote != Some('\'') => escaped = true,
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c.is_whitespace() => return index,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::instr::run::mount::bind::BindOptions;
+    use crate::instr::run::mount::cache::CacheOptions;
+    use crate::instr::run::mount::cache::Sharing;
+
+    fn roundtrip(s: &str) -> Containerfile {
+        let cf = s.parse::<Containerfile>().unwrap();
+        assert_eq!(format!("{cf}").parse::<Containerfile>().unwrap(), cf);
+        cf
+    }
+
+    #[test]
+    fn parse_continuation() {
+        let cf = roundtrip("RUN apt-get update \\\n# comment\n\n    && apt-get install -y git\n");
+        assert_eq!(
+            cf[0],
+            Instr::Run {
+                command: Command::Shell(String::from("apt-get update     && apt-get install -y git")),
+                heredocs: vec![],
+                options: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_directive() {
+        let cf = roundtrip("# syntax=docker/dockerfile:1\n# escape=`\n\n# escape=\\\nENV FOO=\"a``b\" `\nBAR=c\n");
+        assert_eq!(
+            cf[0],
+            Instr::Directive(Directive::Syntax(String::from("docker/dockerfile:1")))
+        );
+        assert_eq!(cf[1], Instr::Directive(Directive::Escape('`')));
+        assert_eq!(cf[3], Instr::Comment(String::from("escape=\\")));
+        assert_eq!(
+            cf[4],
+            Instr::Env(vec![
+                (String::from("FOO"), String::from("a`b")),
+                (String::from("BAR"), String::from("c")),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_exec_form() {
+        let cf = roundtrip("CMD [ \"echo\", \"a \\\"b\\\"\" ]\nENTRYPOINT echo [\nSHELL [\"/bin/sh\", \"-c\"]\n");
+        assert_eq!(
+            cf[0],
+            Instr::Cmd(Command::Exec(vec![String::from("echo"), String::from("a \"b\"")]))
+        );
+        assert_eq!(cf[1], Instr::Entrypoint(Command::Shell(String::from("echo ["))));
+        assert_eq!(cf[2], Instr::Shell(vec![String::from("/bin/sh"), String::from("-c")]));
+    }
+
+    #[test]
+    fn parse_flags() {
+        let cf = roundtrip(concat!(
+            "FROM --platform=$BUILDPLATFORM example.org/foo/bar:baz AS builder\n",
+            "FROM builder AS final\n",
+            "COPY --from=builder --link --chmod=755 a b /dst/\n",
+            "RUN --mount=type=bind,from=builder,source=/src,target=/dst,rw \\\n",
+            "    --mount=type=cache,target=/root/.cache,sharing=locked,mode=0755 \\\n",
+            "    --network=none make\n",
+        ));
+        assert_eq!(
+            cf[0],
+            Instr::From {
+                kind: FromKind::Image {
+                    digest: None,
+                    image: String::from("foo/bar"),
+                    repo: Some(String::from("example.org")),
+                    tag: Some(String::from("baz")),
+                },
+                name: Some(String::from("builder")),
+                platform: Some(String::from("$BUILDPLATFORM")),
+            }
+        );
+        assert_eq!(
+            cf[1],
+            Instr::From {
+                kind: FromKind::Stage(String::from("builder")),
+                name: Some(String::from("final")),
+                platform: None,
+            }
+        );
+        assert_eq!(
+            cf[2],
+            Instr::Copy {
+                destination: PathBuf::from("/dst/"),
+                heredocs: vec![],
+                options: Some(CopyOptions {
+                    chmod: Some(String::from("755")),
+                    from: Some(FromKind::Stage(String::from("builder"))),
+                    link: true,
+                    ..Default::default()
+                }),
+                source: vec![PathBuf::from("a"), PathBuf::from("b")],
+            }
+        );
+        let Instr::Run { options, .. } = &cf[3] else {
+            panic!("{:?}", cf[3]);
+        };
+        assert_eq!(
+            options.as_ref().unwrap().mount,
+            Some(vec![
+                Mount::Bind {
+                    destination: PathBuf::from("/dst"),
+                    options: Some(BindOptions {
+                        from: Some(FromKind::Stage(String::from("builder"))),
+                        readwrite: true,
+                        source: Some(PathBuf::from("/src")),
+                    }),
+                },
+                Mount::Cache {
+                    destination: PathBuf::from("/root/.cache"),
+                    options: Some(CacheOptions {
+                        mode: Some(0o755),
+                        sharing: Some(Sharing::Locked),
+                        ..Default::default()
+                    }),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_heredoc() {
+        let cf = roundtrip(
+            "RUN <<EOF bash && cat <<-'END'\nset -ex\necho $HOME\nEOF\n\tfoo\n\tEND\nCOPY <<EOF /etc/motd\nhi\nEOF\n",
+        );
+        assert_eq!(
+            cf[0],
+            Instr::Run {
+                command: Command::Shell(String::from("<<EOF bash && cat <<-'END'")),
+                heredocs: vec![
+                    Heredoc {
+                        chomp: false,
+                        content: String::from("set -ex\necho $HOME\n"),
+                        delimiter: String::from("EOF"),
+                        expand: true,
+                    },
+                    Heredoc {
+                        chomp: true,
+                        content: String::from("\tfoo\n"),
+                        delimiter: String::from("END"),
+                        expand: false,
+                    },
+                ],
+                options: None,
+            }
+        );
+        assert_eq!(format!("{}", cf[1]), "COPY <<EOF /etc/motd\nhi\nEOF");
+    }
+
+    #[test]
+    fn parse_heredoc_lookalike() {
+        let cf = roundtrip("RUN echo $((1<<2)) $(( 1 <<2 ))\nRUN echo \"a<<b\" 'c <<d'\n");
+        for instr in cf.iter() {
+            match instr {
+                Instr::Run { heredocs, .. } => assert!(heredocs.is_empty(), "{instr:?}"),
+                other => panic!("{other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_key_values() {
+        let cf = roundtrip(
+            "ARG FOO BAR=\"a b\"\nENV PATH=\"/opt/bin:${PATH}\" X='y z'\nENV LEGACY some value\nLABEL \"a.b\"=c\n",
+        );
+        assert_eq!(
+            cf[0],
+            Instr::Arg(vec![
+                (String::from("FOO"), None),
+                (String::from("BAR"), Some(String::from("a b"))),
+            ])
+        );
+        assert_eq!(
+            cf[1],
+            Instr::Env(vec![
+                (String::from("PATH"), String::from("/opt/bin:${PATH}")),
+                (String::from("X"), String::from("y z")),
+            ])
+        );
+        assert_eq!(
+            cf[2],
+            Instr::Env(vec![(String::from("LEGACY"), String::from("some value"))])
+        );
+        assert_eq!(cf[3], Instr::Label(vec![(String::from("a.b"), String::from("c"))]));
+    }
+
+    #[test]
+    fn parse_misc() {
+        roundtrip(concat!(
+            "EXPOSE 80/tcp 443\n",
+            "HEALTHCHECK --interval=5m --retries=3 CMD curl -f http://localhost/\n",
+            "HEALTHCHECK NONE\n",
+            "ONBUILD RUN make\n",
+            "STOPSIGNAL SIGTERM\n",
+            "USER vscode:vscode\n",
+            "VOLUME /data /cache\n",
+            "WORKDIR /workspaces/devpp\n",
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        match "FOO bar".parse::<Containerfile>() {
+            Err(Error::Syntax { line: 1, source }) if matches!(*source, Error::InstrUnknown { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+        match "FROM a\nRUN --foo bar".parse::<Containerfile>() {
+            Err(Error::Syntax { line: 2, source }) if matches!(*source, Error::FlagUnknown { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+        match "RUN <<EOF\nfoo\n".parse::<Containerfile>() {
+            Err(Error::Syntax { source, .. }) if matches!(*source, Error::HeredocUnterminated { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+}
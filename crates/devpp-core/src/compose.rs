@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use devpp_spec::devc::generated::ComposeContainer;
+use devpp_spec::devc::generated::ComposeContainerDockerComposeFile;
+use serde::Serialize;
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Service definition merged across all compose files
+#[derive(Debug)]
+pub(crate) struct Service {
+    pub(crate) build: Option<ServiceBuild>,
+    pub(crate) image: Option<String>,
+    pub(crate) name: String,
+}
+
+/// @see: https://github.com/compose-spec/compose-spec/blob/main/build.md
+#[derive(Debug)]
+pub(crate) struct ServiceBuild {
+    pub(crate) args: BTreeMap<String, String>,
+    pub(crate) context: PathBuf,
+    pub(crate) dockerfile: PathBuf,
+    pub(crate) target: Option<String>,
+}
+
+impl Service {
+    pub(crate) fn new(config_dir: &Path, compose: &ComposeContainer) -> Result<Self> {
+        let files = match &compose.docker_compose_file {
+            ComposeContainerDockerComposeFile::Array(files) => files.iter().map(|file| config_dir.join(file)).collect(),
+            ComposeContainerDockerComposeFile::String(file) => vec![config_dir.join(file)],
+        };
+
+        // @see: https://github.com/compose-spec/compose-spec/blob/main/13-merge.md
+        let mut service = None::<Mapping>;
+        for file in &files {
+            let doc = serde_yaml::from_str::<Value>(&read_to_string(file)?)?;
+            let Some(value) = doc.get("services").and_then(|services| services.get(&compose.service)) else {
+                continue;
+            };
+            let value = match value {
+                Value::Mapping(value) => value.clone(),
+                Value::Null => Mapping::new(),
+                _ => {
+                    return Err(Error::ComposeServiceInvalid {
+                        service: compose.service.clone(),
+                    });
+                }
+            };
+            match &mut service {
+                Some(service) => merge(service, value),
+                None => service = Some(value),
+            }
+        }
+        let service = service.ok_or_else(|| Error::ComposeServiceNotFound {
+            files: files.clone(),
+            service: compose.service.clone(),
+        })?;
+
+        // @see: https://github.com/compose-spec/compose-spec/blob/main/04-version-and-name.md
+        let project_dir = files[0].parent().expect("compose file has a parent");
+        let build = match service.get("build") {
+            None => None,
+            Some(build) => Some(ServiceBuild::new(project_dir, &compose.service, build)?),
+        };
+        let image = service.get("image").and_then(Value::as_str).map(String::from);
+        if build.is_none() && image.is_none() {
+            return Err(Error::ComposeServiceInvalid {
+                service: compose.service.clone(),
+            });
+        }
+
+        Ok(Self {
+            build,
+            image,
+            name: compose.service.clone(),
+        })
+    }
+
+    /// Points the service at the generated Containerfile
+    pub(crate) fn write_override(&self, w: impl Write, context: &Path, containerfile: &Path, init: bool) -> Result<()> {
+        let service = OverrideService {
+            build: OverrideBuild {
+                context: context.to_path_buf(),
+                dockerfile: containerfile.to_path_buf(),
+                target: self.build.as_ref().and_then(|build| build.target.clone()),
+            },
+            // NOTE: don't clobber the upstream image when the service used to pull it
+            image: self.build.is_none().then(|| format!("devpp-{}", self.name)),
+            init: init.then_some(true),
+        };
+        let services = BTreeMap::from([(self.name.clone(), service)]);
+        serde_yaml::to_writer(w, &Override { services })?;
+        Ok(())
+    }
+}
+
+impl ServiceBuild {
+    fn new(project_dir: &Path, service: &str, build: &Value) -> Result<Self> {
+        let (context, build) = match build {
+            Value::Mapping(build) => (build.get("context").and_then(Value::as_str).unwrap_or("."), Some(build)),
+            Value::String(context) => (context.as_str(), None),
+            _ => {
+                return Err(Error::ComposeServiceInvalid {
+                    service: service.to_string(),
+                });
+            }
+        };
+        let context = project_dir.join(context).canonicalize()?;
+
+        let mut args = BTreeMap::new();
+        let mut dockerfile = context.join("Dockerfile");
+        let mut target = None;
+        if let Some(build) = build {
+            match build.get("args") {
+                Some(Value::Mapping(map)) => {
+                    for (key, value) in map {
+                        if let (Some(key), Some(value)) = (key.as_str(), scalar(value)) {
+                            args.insert(key.to_string(), value);
+                        }
+                    }
+                }
+                Some(Value::Sequence(seq)) => {
+                    for item in seq.iter().filter_map(Value::as_str) {
+                        // NOTE: bare `KEY` is resolved from the environment by compose itself
+                        if let Some((key, value)) = item.split_once('=') {
+                            args.insert(key.to_string(), value.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if let Some(path) = build.get("dockerfile").and_then(Value::as_str) {
+                dockerfile = context.join(path);
+            }
+            target = build.get("target").and_then(Value::as_str).map(String::from);
+        }
+
+        Ok(Self {
+            args,
+            context,
+            dockerfile,
+            target,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Override {
+    services: BTreeMap<String, OverrideService>,
+}
+
+#[derive(Serialize)]
+struct OverrideService {
+    build: OverrideBuild,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct OverrideBuild {
+    context: PathBuf,
+    dockerfile: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+fn merge(lhs: &mut Mapping, rhs: Mapping) {
+    for (key, value) in rhs {
+        match (lhs.get_mut(&key), value) {
+            (Some(Value::Mapping(lhs)), Value::Mapping(rhs)) => merge(lhs, rhs),
+            (Some(lhs), rhs) => *lhs = rhs,
+            (None, rhs) => {
+                lhs.insert(key, rhs);
+            }
+        }
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
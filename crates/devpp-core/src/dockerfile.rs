@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use containerfile::Containerfile;
+use containerfile::instr::Instr;
+use containerfile::instr::from::FromKind;
+use containerfile::instr::run::mount::Mount;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// User's Dockerfile with its target stage renamed to the base stage
+#[derive(Debug)]
+pub(crate) struct Dockerfile {
+    pub(crate) body: Vec<Instr>,
+    /// Parser directives that must stay on top of the generated file
+    pub(crate) directives: Vec<Instr>,
+}
+
+impl Dockerfile {
+    pub(crate) fn new(
+        path: &Path,
+        target: Option<&str>,
+        base_stage: &str,
+        args: &BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let mut cf = read_to_string(path)?
+            .parse::<Containerfile>()
+            .map_err(|source| Error::DockerfileInvalid {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        // @see: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md#arg
+        for instr in cf.iter_mut() {
+            if let Instr::Arg(inner) = instr {
+                for (name, default) in inner {
+                    if let Some(value) = args.get(name) {
+                        *default = Some(value.clone());
+                    }
+                }
+            }
+        }
+
+        let stages = cf
+            .iter()
+            .enumerate()
+            .filter_map(|(index, instr)| match instr {
+                Instr::From { name, .. } => Some((index, name.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let (index, name) = match target {
+            Some(target) => stages
+                .into_iter()
+                .find(|(_, name)| name.as_ref().is_some_and(|name| name.eq_ignore_ascii_case(target)))
+                .ok_or_else(|| Error::TargetNotFound {
+                    path: path.to_path_buf(),
+                    target: target.to_string(),
+                })?,
+            None => stages.into_iter().next_back().ok_or_else(|| Error::StageNotFound {
+                path: path.to_path_buf(),
+            })?,
+        };
+
+        if let Instr::From { name, .. } = &mut cf[index] {
+            *name = Some(base_stage.to_string());
+        }
+        if let Some(name) = name {
+            for instr in cf.iter_mut().skip(index + 1) {
+                rename_stage(instr, &name, base_stage);
+            }
+        }
+
+        let mut directives = std::mem::take(&mut *cf);
+        let split = directives
+            .iter()
+            .position(|instr| !matches!(instr, Instr::Directive(_)))
+            .unwrap_or(directives.len());
+        let mut body = directives.split_off(split);
+        let start = body
+            .iter()
+            .position(|instr| !matches!(instr, Instr::Empty))
+            .unwrap_or(0);
+        body.drain(..start);
+        while matches!(body.last(), Some(Instr::Empty)) {
+            body.pop();
+        }
+        Ok(Self { body, directives })
+    }
+}
+
+fn rename_stage(instr: &mut Instr, from: &str, to: &str) {
+    let rename = |kind: &mut FromKind| {
+        if let FromKind::Stage(stage) = kind
+            && stage.eq_ignore_ascii_case(from)
+        {
+            *stage = to.to_string();
+        }
+    };
+    match instr {
+        Instr::Copy {
+            options: Some(options), ..
+        } => {
+            if let Some(kind) = &mut options.from {
+                rename(kind);
+            }
+        }
+        Instr::From { kind, .. } => rename(kind),
+        Instr::Onbuild(instr) => rename_stage(instr, from, to),
+        Instr::Run {
+            options: Some(options), ..
+        } => {
+            for mount in options.mount.iter_mut().flatten() {
+                let kind = match mount {
+                    Mount::Bind { options, .. } => options.as_mut().and_then(|options| options.from.as_mut()),
+                    Mount::Cache { options, .. } => options.as_mut().and_then(|options| options.from.as_mut()),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    rename(kind);
+                }
+            }
+        }
+        _ => {}
+    }
+}
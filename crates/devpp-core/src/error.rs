@@ -1,5 +1,10 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error(transparent)]
+    Containerfile(#[from] containerfile::error::Error),
     #[error(transparent)]
     DevppSpec(#[from] devpp_spec::error::Error),
     #[error(transparent)]
@@ -7,11 +12,92 @@ pub enum Error {
     #[error(transparent)]
     Path(#[from] std::path::StripPrefixError),
     #[error(transparent)]
-    StableTopoSort(#[from] stable_topo_sort::error::Error),
-    #[error("dependencies of merge features are not supported")]
-    NestedMergeNotSupported,
-    #[error("target stage must be set")]
-    TargetNotFound,
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    SerdeYaml(#[from] serde_yaml::Error),
+    #[error("{path:?} does not use dockerComposeFile")]
+    ComposeNotFound { path: PathBuf },
+    #[error("service {service:?} has neither build nor image")]
+    ComposeServiceInvalid { service: String },
+    #[error("service {service:?} is not found in {files:?}")]
+    ComposeServiceNotFound { files: Vec<PathBuf>, service: String },
+    #[error("{path:?} has no parent folder")]
+    ConfigDirNotFound { path: PathBuf },
+    #[error("failed to parse {path:?}: {source}")]
+    DockerfileInvalid {
+        path: PathBuf,
+        #[source]
+        source: containerfile::error::Error,
+    },
+    #[error("features form a cycle: {}", render_cycle(cycle))]
+    FeatureCycle { cycle: Vec<CycleEdge> },
+    #[error("feature {id:?} is required with conflicting options: {lhs:?} and {rhs:?}")]
+    FeatureOptionsConflict {
+        id: String,
+        lhs: BTreeMap<String, String>,
+        rhs: BTreeMap<String, String>,
+    },
+    #[error("the config of {image:?} is only looked up with the artifact feature")]
+    ImageConfigUnavailable { image: String },
+    #[error("failed to look up the config of {image:?}: {source}")]
+    ImageInspect {
+        image: String,
+        #[source]
+        source: devpp_spec::error::Error,
+    },
+    #[error("overrideFeatureInstallOrder references a feature that is not installed: {id:?}")]
+    InstallOrderNotFound { id: String },
+    #[error("overrideFeatureInstallOrder places {id:?} before {dependency:?}, but it depends on it")]
+    InstallOrderViolation { dependency: String, id: String },
+    #[error("feature {id:?} is not found in the lockfile")]
+    LockfileFeatureNotFound { id: String },
+    #[error("feature {id:?} resolves differently from {path:?}, run `devpp lock update`")]
+    LockfileMismatch { id: String, path: PathBuf },
+    #[error("{path:?} does not declare any stage")]
+    StageNotFound { path: PathBuf },
+    #[error("target stage {target:?} is not found in {path:?}")]
+    TargetNotFound { path: PathBuf, target: String },
+    #[error("feature {feature:?} has neither test.sh nor scenarios.json in {path:?}")]
+    TestNotFound { feature: String, path: PathBuf },
+    #[error("scenario script {path:?} is not found")]
+    TestScriptNotFound { path: PathBuf },
+    #[error("vendored features are stale, run `devpp vendor`: {}", render_stale(stale))]
+    VendorStale { stale: Vec<Stale> },
+}
+
+/// Edge of a feature cycle, `to` installs after `from`
+#[derive(Debug)]
+pub struct CycleEdge {
+    pub from: String,
+    /// devcontainer-feature.json that declared the edge
+    pub metadata: PathBuf,
+    pub to: String,
+}
+
+fn render_cycle(cycle: &[CycleEdge]) -> String {
+    let mut path = cycle.iter().map(|edge| edge.from.as_str()).collect::<Vec<_>>();
+    path.extend(cycle.last().map(|edge| edge.to.as_str()));
+    let edges = cycle
+        .iter()
+        .map(|edge| format!("{:?} installs after {:?} in {:?}", edge.to, edge.from, edge.metadata))
+        .collect::<Vec<_>>();
+    format!("{} ({})", path.join(" -> "), edges.join(", "))
+}
+
+/// Vendored feature that the lockfile no longer pins
+#[derive(Debug)]
+pub struct Stale {
+    pub path: PathBuf,
+    /// Feature reference the copy was vendored from
+    pub reference: String,
+}
+
+fn render_stale(stale: &[Stale]) -> String {
+    let stale = stale
+        .iter()
+        .map(|stale| format!("{:?} from {:?}", stale.path, stale.reference))
+        .collect::<Vec<_>>();
+    stale.join(", ")
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
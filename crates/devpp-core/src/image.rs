@@ -0,0 +1,46 @@
+use devpp_spec::image::ImageConfig;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Looks up the config of the image the build starts from, e.g. in its registry
+pub trait Inspector {
+    fn inspect(&mut self, image: &str) -> Result<ImageConfig>;
+}
+
+/// Inspector `devpp` uses, the registry one when it is built in
+#[cfg(feature = "artifact")]
+pub type DefaultInspector = RegistryInspector;
+#[cfg(not(feature = "artifact"))]
+pub type DefaultInspector = OfflineInspector;
+
+/// Asks the registry the image is published to, with the credentials docker uses
+#[cfg(feature = "artifact")]
+#[derive(Debug, Default)]
+pub struct RegistryInspector;
+
+#[cfg(feature = "artifact")]
+impl Inspector for RegistryInspector {
+    fn inspect(&mut self, image: &str) -> Result<ImageConfig> {
+        let inspect = || -> devpp_spec::error::Result<ImageConfig> {
+            let mut registry = devpp_spec::oci::Registry::new(devpp_spec::oci::DockerConfig::load()?);
+            registry.image_config(&image.parse()?)
+        };
+        inspect().map_err(|source| Error::ImageInspect {
+            image: image.to_string(),
+            source,
+        })
+    }
+}
+
+/// Knows no image, the build leaves what the image sets as is
+#[derive(Debug, Default)]
+pub struct OfflineInspector;
+
+impl Inspector for OfflineInspector {
+    fn inspect(&mut self, image: &str) -> Result<ImageConfig> {
+        Err(Error::ImageConfigUnavailable {
+            image: image.to_string(),
+        })
+    }
+}
@@ -1,15 +1,31 @@
+mod compose;
+mod dockerfile;
 pub mod error;
+pub mod image;
+mod order;
+pub mod scenario;
+mod vendor;
 
 use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fs::copy;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
 use std::fs::read_to_string;
+use std::fs::remove_dir_all;
+use std::fs::write;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 
 use containerfile::Containerfile;
 use containerfile::instr::Instr;
+use containerfile::instr::command::Command;
 use containerfile::instr::copy::CopyOptions;
 use containerfile::instr::from::FromKind;
+use containerfile::instr::heredoc::Heredoc;
 use containerfile::instr::run::RunOptions;
 use containerfile::instr::run::mount::Mount;
 use containerfile::instr::run::mount::bind::BindOptions;
@@ -27,41 +43,80 @@ use devpp_spec::devpp::generated::MountSharing as DevppMountSharing;
 use devpp_spec::devpp::generated::MountType as DevppMountType;
 use devpp_spec::feat::Feature;
 use devpp_spec::feat::Reference;
+use devpp_spec::feat::env_name;
 use devpp_spec::feat::generated::FeatureOption;
 use devpp_spec::get_metadata;
-use stable_topo_sort::stable_topo_sort;
+use devpp_spec::image::METADATA_LABEL;
+use devpp_spec::lock::Lockfile;
+use devpp_spec::merge::merge;
 
+use crate::compose::Service;
+use crate::dockerfile::Dockerfile;
 use crate::error::Error;
 use crate::error::Result;
+use crate::error::Stale;
+use crate::image::Inspector;
+use crate::order::find_key;
+use crate::order::install_order;
+use crate::order::stage_levels;
+use crate::scenario::Builder;
+use crate::scenario::Outcome;
 
-pub fn build(mut w: impl Write, workspace: &Path, config: Option<&Path>) -> Result<()> {
+/// Options of a feature, as its scripts see them
+const FEATURE_ENV: &str = "devcontainer-features.env";
+
+/// Users the feature installs for, sourced before its options
+///
+/// @see: https://containers.dev/implementors/features/#user-env-var
+const BUILTIN_ENV: &str = "devcontainer-features.builtin.env";
+
+/// Wrapper running the feature entrypoints before the command
+const ENTRYPOINT: &str = "/usr/local/share/devpp/entrypoint.sh";
+
+/// Stage the features are installed on top of
+const BASE_STAGE: &str = "devpp-base";
+
+/// User features are installed as
+const ROOT: &str = "root";
+
+/// How `build` treats devcontainer-lock.json
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Lock {
+    /// Fail when the resolved features differ from the lockfile
+    Frozen,
+    /// Pin features to the lockfile and record the ones resolved anew
+    #[default]
+    Update,
+}
+
+pub fn build(
+    mut w: impl Write,
+    inspector: &mut impl Inspector,
+    workspace: &Path,
+    config: Option<&Path>,
+    lock: Lock,
+) -> Result<()> {
+    let (cf, _) = containerfile(inspector, workspace, config, lock)?;
+    writeln!(w, "{cf}")?;
+    Ok(())
+}
+
+/// Containerfile for the config, along with the build context it expects
+fn containerfile(
+    inspector: &mut impl Inspector,
+    workspace: &Path,
+    config: Option<&Path>,
+    lock: Lock,
+) -> Result<(Containerfile, PathBuf)> {
     let config = Config::find_config(workspace, config)?;
-    let config_dir = config.path.parent().unwrap(); // TODO: handle error
+    let config_dir = get_config_dir(&config)?;
     let devc = DevContainer::new(read_to_string(&config.path)?)?;
 
-    let mut features = BTreeMap::new();
-    for (id, options) in &devc.common.features {
-        let reference = Reference::new(id, &config)?;
-        let feature = Feature::new(&reference)?;
-        features.insert(
-            id,
-            Entry {
-                cstm: Customizations::new(&feature),
-                feat: feature,
-                opts: options,
-            },
-        );
-    }
-
-    let (mut nodes, mut edges) = (vec![], vec![]);
-    for (id, entry) in &features {
-        nodes.push(*id);
-        for dep_id in &entry.feat.inner.installs_after {
-            edges.push((dep_id, *id));
-        }
-    }
-    let ids = stable_topo_sort(&nodes, &edges)?;
+    let lockfile = Lockfile::load(&config)?;
+    let mut features = resolve(&config, &devc, &lockfile.clone().unwrap_or_default())?;
+    apply_lockfile(&config, lockfile, get_lockfile(&features), lock)?;
 
+    let mut directives = vec![];
     let mut base_sink = vec![
         Instr::Comment(String::from(
             "@help: https://github.com/moby/buildkit/blob/dockerfile/1.20.0-labs/frontend/dockerfile/docs/reference.md",
@@ -73,92 +128,190 @@ pub fn build(mut w: impl Write, workspace: &Path, config: Option<&Path>) -> Resu
     ];
     let mut feat_sink = vec![];
 
-    let base_stage = String::from("devpp-base");
-    let (context, target) = match &devc.is_compose {
-        IsCompose::Compose(_compose) => unimplemented!(),
-        IsCompose::NonCompose(non_compose) => match &non_compose.is_image {
-            IsImage::Dockerfile(dockerfile) => match dockerfile {
-                DockerfileContainer::Variant0 { build } => {
-                    let path = Path::new(build.context.as_deref().unwrap_or("."));
-                    let context = if path.is_relative() {
-                        config_dir.join(path).canonicalize()?
-                    } else {
-                        path.canonicalize()?
-                    };
-                    base_sink.push(Instr::Comment(String::from("TODO: parse file and patch base stage")));
-                    (context, build.target.clone())
-                }
-                DockerfileContainer::Variant1 { .. } => unimplemented!(),
-            },
-            IsImage::Image(image) => {
-                base_sink.push(Instr::From {
-                    kind: FromKind::Image {
-                        digest: None,
-                        image: image.image.clone(),
-                        repo: None,
-                        tag: None,
-                    },
-                    name: Some(base_stage.clone()),
-                    platform: None,
-                });
-                (config_dir.to_path_buf(), None)
-            }
-        },
-    };
+    let base_stage = String::from(BASE_STAGE);
+    let (context, target) = push_base(&mut directives, &mut base_sink, config_dir, &devc, &base_stage)?;
     base_sink.push(Instr::Empty);
 
+    let mut base = BaseStage::new(&base_sink, &base_stage);
+    for entry in features.values_mut() {
+        entry.stage(&context)?;
+    }
+    let ids = install_order(&features, &devc.common.override_feature_install_order)?;
+    // NOTE: the feature entrypoints go in front of the image's, and ENTRYPOINT resets its CMD. The
+    // metadata label replaces the image's, so it carries over its entries
+    if !features.is_empty()
+        && let Err(err) = base.inherit(inspector)
+    {
+        feat_sink.push(Instr::Comment(format!(
+            "@warning: {err}, the user and the entrypoint it sets are left as is, its metadata is left out"
+        )));
+        feat_sink.push(Instr::Empty);
+    }
+    let mut metadata = std::mem::take(&mut base.metadata);
+    let feats = ids
+        .iter()
+        .map(|id| (id.as_str(), &features[*id].feat))
+        .collect::<Vec<_>>();
+    metadata.extend(get_metadata(&devc, &feats)?);
+    let merged = merge(&metadata);
+    let entrypoints = ids
+        .iter()
+        .filter_map(|id| features[*id].feat.inner.entrypoint.as_deref())
+        .collect::<Vec<_>>();
+    let declared_user = merged.get("containerUser").and_then(serde_json::Value::as_str);
+    let container_user = declared_user.or(base.user.as_deref());
+    let remote_user = merged
+        .get("remoteUser")
+        .and_then(serde_json::Value::as_str)
+        .or(container_user);
+    // NOTE: the features see root, which they install as, in place of a user the image keeps to itself
+    let builtin = get_builtin_env(container_user.unwrap_or(ROOT), remote_user.unwrap_or(ROOT));
+
+    // NOTE: features install as root, merge features in the base stage, which the final stage then
+    // switches back to the container user. Without one to switch back to, they install as the image user
+    let switched = container_user.is_some() && ids.iter().any(|id| features[*id].is_merge());
+    if switched {
+        base_sink.insert(
+            base.end,
+            Instr::User {
+                group: None,
+                user: ROOT.to_string(),
+            },
+        );
+    }
+
+    // NOTE: the env files live in stages of their own rather than in the build context, ahead of the base
+    // stage that merge features install in
+    let mut env_sink = vec![];
+    for id in &ids {
+        features[*id].push_env(&mut env_sink, &builtin);
+        env_sink.push(Instr::Empty);
+    }
+    base_sink.splice(base.start..base.start, env_sink);
+
+    // NOTE: non-merged dependencies of merge features are built from the base stage preceding them
+    let (mut stage, mut cuts) = (base_stage.clone(), 0);
+    let mut stages = HashMap::new();
+    let mut pulled = HashSet::new();
     for id in &ids {
-        let entry = features.get(id).expect("entry exists");
+        let entry = features.get(*id).expect("entry exists");
+        if !entry.is_merge() {
+            continue;
+        }
 
-        if entry.is_merge() {
-            if !entry.feat.inner.installs_after.is_empty() {
-                return Err(Error::NestedMergeNotSupported);
+        let deps = entry.get_non_merged_ancestors(&features);
+        if deps.iter().any(|dep| !stages.contains_key(dep)) {
+            for dep in &deps {
+                stages.entry(*dep).or_insert_with(|| stage.clone());
             }
-            entry.push_feature(&mut base_sink, &context)?;
+            cuts += 1;
+            let name = format!("{base_stage}-{cuts}");
+            base_sink.push(Instr::From {
+                kind: FromKind::Stage(std::mem::replace(&mut stage, name.clone())),
+                name: Some(name),
+                platform: None,
+            });
             base_sink.push(Instr::Empty);
-            continue;
         }
 
-        feat_sink.push(Instr::From {
-            kind: FromKind::Stage(base_stage.clone()),
-            name: Some(entry.get_feature_id()),
-            platform: None,
-        });
+        for dep_id in ids.iter().filter(|dep_id| deps.contains(*dep_id)) {
+            if pulled.insert(*dep_id) {
+                features
+                    .get(*dep_id)
+                    .expect("entry exists")
+                    .push_dependency(&mut base_sink, &context)?;
+                base_sink.push(Instr::Empty);
+            }
+        }
+
+        entry.push_feature(&mut base_sink, &context)?;
+        base_sink.push(Instr::Empty);
+    }
+
+    // NOTE: stages within a level don't depend on each other, so BuildKit builds them concurrently
+    let (levels, path) = stage_levels(&features, &ids)?;
+    if path.len() > 1 {
+        let path = path.iter().map(|id| features[*id].get_feature_id()).collect::<Vec<_>>();
+        feat_sink.push(Instr::Comment(format!("@critical-path: {}", path.join(" -> "))));
         feat_sink.push(Instr::Empty);
+    }
 
-        for id in &entry.feat.inner.installs_after {
-            let entry = features.get(id).expect("entry exists");
-            if entry.is_merge() {
-                continue;
+    for (level, ids) in levels.iter().enumerate() {
+        for id in ids {
+            let entry = features.get(*id).expect("entry exists");
+
+            feat_sink.push(Instr::Comment(format!("@level: {level}")));
+            feat_sink.push(Instr::From {
+                kind: FromKind::Stage(stages.get(id).unwrap_or(&stage).clone()),
+                name: Some(entry.get_feature_id()),
+                platform: None,
+            });
+            feat_sink.push(Instr::User {
+                group: None,
+                user: ROOT.to_string(),
+            });
+            feat_sink.push(Instr::Empty);
+
+            for id in entry.get_dependencies() {
+                let entry = features.get(id).expect("entry exists");
+                if entry.is_merge() {
+                    continue;
+                }
+                entry.push_dependency(&mut feat_sink, &context)?;
+                feat_sink.push(Instr::Empty);
             }
-            entry.push_dependency(&mut feat_sink, &context)?;
+
+            entry.push_feature(&mut feat_sink, &context)?;
             feat_sink.push(Instr::Empty);
         }
-
-        entry.push_feature(&mut feat_sink, &context)?;
-        feat_sink.push(Instr::Empty);
     }
 
     feat_sink.push(Instr::From {
-        kind: FromKind::Stage(base_stage.clone()),
+        kind: FromKind::Stage(stage),
         name: target,
         platform: None,
     });
     feat_sink.push(Instr::Empty);
 
     for id in &ids {
-        let entry = features.get(id).expect("entry exists");
-        if entry.is_merge() {
+        let entry = features.get(*id).expect("entry exists");
+        if entry.is_merge() || pulled.contains(id) {
             continue;
         }
         entry.push_dependency(&mut feat_sink, &context)?;
         feat_sink.push(Instr::Empty);
     }
 
+    // NOTE: in front of an unknown entrypoint, the wrapper would replace it. Runtimes still run the
+    // feature entrypoints from the metadata label
+    if !entrypoints.is_empty() && !base.inherits {
+        push_entrypoint(&mut feat_sink, &context, &entrypoints, &base)?;
+        feat_sink.push(Instr::Empty);
+    }
+
+    if let Some(container_user) = container_user
+        && (switched || declared_user.is_some_and(|user| Some(user) != base.user.as_deref()))
+    {
+        feat_sink.push(Instr::User {
+            group: None,
+            user: container_user.to_string(),
+        });
+    }
+    let workspace_folder = match &devc.is_compose {
+        IsCompose::Compose(compose) => Some(&compose.workspace_folder),
+        IsCompose::NonCompose(non_compose) => non_compose.base.workspace_folder.as_ref(),
+    };
+    if let Some(workspace_folder) = workspace_folder {
+        feat_sink.push(Instr::Workdir(PathBuf::from(workspace_folder)));
+    }
+    if matches!(feat_sink.last(), Some(Instr::User { .. } | Instr::Workdir(_))) {
+        feat_sink.push(Instr::Empty);
+    }
+
     if !features.is_empty() {
         feat_sink.push(Instr::Label(vec![(
-            String::from("devcontainer.metadata"),
-            get_metadata(&devc, &features.values().map(|entry| &entry.feat).collect::<Vec<_>>())?,
+            METADATA_LABEL.to_string(),
+            serde_json::to_string(&metadata)?,
         )]));
         feat_sink.push(Instr::Empty);
     }
@@ -168,21 +321,631 @@ pub fn build(mut w: impl Write, workspace: &Path, config: Option<&Path>) -> Resu
     }
 
     let mut cf = Containerfile::default();
+    cf.append(&mut directives);
     cf.append(&mut base_sink);
     cf.append(&mut feat_sink);
-    writeln!(w, "{cf}")?;
+    Ok((cf, context))
+}
+
+/// Features referenced by the config and, transitively, by their dependsOn
+/// Pushes the stages the config builds on, the last one named `base_stage`, and returns the build context
+/// along with the target stage
+fn push_base(
+    directives: &mut Vec<Instr>,
+    sink: &mut Vec<Instr>,
+    config_dir: &Path,
+    devc: &DevContainer,
+    base_stage: &str,
+) -> Result<(PathBuf, Option<String>)> {
+    Ok(match &devc.is_compose {
+        IsCompose::Compose(compose) => {
+            let service = Service::new(config_dir, compose)?;
+            match (service.build, service.image) {
+                (Some(build), _) => {
+                    let mut dockerfile =
+                        Dockerfile::new(&build.dockerfile, build.target.as_deref(), base_stage, &build.args)?;
+                    directives.append(&mut dockerfile.directives);
+                    sink.append(&mut dockerfile.body);
+                    (build.context, build.target)
+                }
+                (None, Some(image)) => {
+                    push_image(sink, image, base_stage);
+                    (config_dir.to_path_buf(), None)
+                }
+                (None, None) => unreachable!("validated by Service::new"),
+            }
+        }
+        IsCompose::NonCompose(non_compose) => match &non_compose.is_image {
+            IsImage::Dockerfile(dockerfile) => {
+                let (context, dockerfile, args, target) = match dockerfile {
+                    DockerfileContainer::Variant0 { build } => (
+                        &build.context,
+                        &build.dockerfile,
+                        build.args.clone(),
+                        build.target.clone(),
+                    ),
+                    DockerfileContainer::Variant1 {
+                        build,
+                        context,
+                        docker_file,
+                    } => {
+                        let build = build.clone().unwrap_or_default();
+                        (context, docker_file, build.args, build.target)
+                    }
+                };
+                let path = Path::new(context.as_deref().unwrap_or("."));
+                let context = if path.is_relative() {
+                    config_dir.join(path).canonicalize()?
+                } else {
+                    path.canonicalize()?
+                };
+                let mut dockerfile =
+                    Dockerfile::new(&config_dir.join(dockerfile), target.as_deref(), base_stage, &args)?;
+                directives.append(&mut dockerfile.directives);
+                sink.append(&mut dockerfile.body);
+                (context, target)
+            }
+            IsImage::Image(image) => {
+                push_image(sink, image.image.clone(), base_stage);
+                (config_dir.to_path_buf(), None)
+            }
+        },
+    })
+}
+
+fn resolve(config: &Config, devc: &DevContainer, lockfile: &Lockfile) -> Result<BTreeMap<String, Entry>> {
+    // @see: https://containers.dev/implementors/features/#definition-feature-equality
+    let mut features = BTreeMap::<String, Entry>::new();
+    let mut queue = devc
+        .common
+        .features
+        .iter()
+        .map(|(id, options)| {
+            let options = options.iter().map(|(key, value)| (key.clone(), value.to_string()));
+            (id.clone(), options.collect(), None)
+        })
+        .collect::<VecDeque<_>>();
+    while let Some((id, options, dependent)) = queue.pop_front() {
+        let reference = Reference::new(&id, config)?;
+        let feature = Feature::new(&reference, lockfile.features.get(&id))?;
+        let mut name = feature.inner.id.clone();
+        for n in 2.. {
+            if !features.values().any(|other| other.name == name) {
+                break;
+            }
+            name = format!("{}-{n}", feature.inner.id);
+        }
+        let entry = Entry {
+            cstm: Customizations::new(&feature),
+            depends_on: vec![],
+            installs_after: vec![],
+            name,
+            opts: feature.options(&options)?,
+            source: get_source(&feature),
+            feat: feature,
+        };
+
+        // NOTE: unrelated features may share an id, e.g. `./node` and `ghcr.io/devcontainers/features/node`
+        let key = match features.iter().find(|(_, other)| other.source == entry.source) {
+            Some((key, other)) => {
+                if other.opts != entry.opts {
+                    return Err(Error::FeatureOptionsConflict {
+                        id: entry.feat.inner.id.clone(),
+                        lhs: other.opts.clone(),
+                        rhs: entry.opts,
+                    });
+                }
+                key.clone()
+            }
+            None => {
+                // @see: https://containers.dev/implementors/features/#dependson
+                for (dep_id, options) in &entry.feat.inner.depends_on {
+                    queue.push_back((dep_id.clone(), get_depends_on_options(options), Some(id.clone())));
+                }
+                features.insert(id.clone(), entry);
+                id
+            }
+        };
+
+        if let Some(dependent) = dependent {
+            features.get_mut(&dependent).expect("entry exists").depends_on.push(key);
+        }
+    }
 
+    // @see: https://containers.dev/implementors/features/#installsafter
+    // NOTE: installsAfter is a soft dependency, so features that are not installed are ignored
+    let keys = features.keys().cloned().collect::<Vec<_>>();
+    for entry in features.values_mut() {
+        entry.installs_after = entry
+            .feat
+            .inner
+            .installs_after
+            .iter()
+            .filter_map(|id| find_key(&keys, id).cloned())
+            .collect();
+    }
+
+    Ok(features)
+}
+
+/// What the feature was fetched from: the digest, commit or URL it resolved to, or the folder of a local feature
+fn get_source(feature: &Feature) -> String {
+    match &feature.locked {
+        Some(locked) => locked.resolved.clone(),
+        None => {
+            let dir = feature.metadata.parent().expect("metadata has a parent");
+            dir.to_string_lossy().into_owned()
+        }
+    }
+}
+
+/// Lockfile pinning the remote features to their resolution
+fn get_lockfile(features: &BTreeMap<String, Entry>) -> Lockfile {
+    Lockfile {
+        features: features
+            .iter()
+            .filter_map(|(id, entry)| Some((id.clone(), entry.feat.locked.clone()?)))
+            .collect(),
+    }
+}
+
+fn apply_lockfile(config: &Config, lockfile: Option<Lockfile>, resolved: Lockfile, lock: Lock) -> Result<()> {
+    match (lock, lockfile) {
+        (Lock::Frozen, lockfile) => {
+            let lockfile = lockfile.unwrap_or_default();
+            if let Some(id) = lockfile.diff(&resolved) {
+                return Err(Error::LockfileMismatch {
+                    id: id.clone(),
+                    path: Lockfile::path(config),
+                });
+            }
+        }
+        (Lock::Update, Some(lockfile)) if lockfile == resolved => {}
+        (Lock::Update, None) if resolved.features.is_empty() => {}
+        (Lock::Update, _) => resolved.save(config)?,
+    }
+    Ok(())
+}
+
+/// Re-resolves `feature`, or every feature when it is omitted, and writes the lockfile
+///
+/// Unpinned tags and revisions are resolved against the remote again, contents already cached are reused by digest.
+pub fn lock_update(workspace: &Path, config: Option<&Path>, feature: Option<&str>) -> Result<()> {
+    let config = Config::find_config(workspace, config)?;
+    let devc = DevContainer::new(read_to_string(&config.path)?)?;
+
+    let mut lockfile = Lockfile::load(&config)?.unwrap_or_default();
+    match feature {
+        Some(id) => {
+            let key = find_key(lockfile.features.keys(), id)
+                .cloned()
+                .ok_or_else(|| Error::LockfileFeatureNotFound { id: id.to_string() })?;
+            lockfile.features.remove(&key);
+        }
+        None => lockfile.features.clear(),
+    }
+    get_lockfile(&resolve(&config, &devc, &lockfile)?).save(&config)?;
+    Ok(())
+}
+
+/// Copies the remote features into `.devcontainer/vendor/<id>/` and writes the config referencing them,
+/// `dir` is where the config is going to live. Returns the vendored copies the lockfile no longer pins
+///
+/// Features sharing an id, e.g. `ghcr.io/a/x/go` and `ghcr.io/b/y/go`, are vendored into `go/` and `go-2/`.
+pub fn vendor(mut w: impl Write, workspace: &Path, config: Option<&Path>, dir: Option<&Path>) -> Result<Vec<Stale>> {
+    let config = Config::find_config(workspace, config)?;
+    let devc = DevContainer::new(read_to_string(&config.path)?)?;
+    let vendor = config.find_dotdev()?.join("vendor");
+    let dir = match dir {
+        Some(dir) => dir.canonicalize()?,
+        None => get_config_dir(&config)?.to_path_buf(),
+    };
+
+    let lockfile = Lockfile::load(&config)?;
+    let features = resolve(&config, &devc, &lockfile.clone().unwrap_or_default())?;
+    let resolved = get_lockfile(&features);
+    apply_lockfile(&config, lockfile, resolved.clone(), Lock::Update)?;
+
+    let locals = features
+        .iter()
+        .filter(|(_, entry)| entry.feat.locked.is_some())
+        .map(|(key, entry)| (key.clone(), vendor::relative(&dir, &vendor.join(&entry.name))))
+        .collect::<BTreeMap<_, _>>();
+    for (key, entry) in &features {
+        vendor::copy_feature(entry, key, &vendor.join(&entry.name), &locals)?;
+    }
+
+    let mut s = read_to_string(&config.path)?;
+    json_strip_comments::strip(&mut s)?;
+    let mut value = serde_json::from_str::<serde_json::Value>(&s)?;
+    vendor::rewrite_references(&mut value, &locals);
+    writeln!(w, "{}", serde_json::to_string_pretty(&value)?)?;
+
+    vendor::find_stale(&vendor, &resolved)
+}
+
+/// Fails when a vendored feature is no longer pinned by the lockfile
+pub fn vendor_check(workspace: &Path, config: Option<&Path>) -> Result<()> {
+    let config = Config::find_config(workspace, config)?;
+    let lockfile = Lockfile::load(&config)?.unwrap_or_default();
+    let stale = vendor::find_stale(&config.find_dotdev()?.join("vendor"), &lockfile)?;
+    if !stale.is_empty() {
+        return Err(Error::VendorStale { stale });
+    }
+    Ok(())
+}
+
+/// Builds the default test and every scenario of `feature` in the collection at `collection`,
+/// each from its own workspace under `.devpp/test/<feature>/<scenario>/`
+///
+/// @see: https://github.com/devcontainers/cli/blob/main/docs/features/test.md
+pub fn test_feature(
+    builder: &mut impl Builder,
+    inspector: &mut impl Inspector,
+    collection: &Path,
+    feature: &str,
+    base_image: &str,
+) -> Result<Vec<Outcome>> {
+    let collection = collection.canonicalize()?;
+    let (src, tests) = (collection.join("src"), collection.join("test").join(feature));
+    let mut outcomes = vec![];
+    for (name, mut config) in scenario::load(&tests, feature, base_image)? {
+        let workspace = collection.join(".devpp").join("test").join(feature).join(&name);
+        if workspace.try_exists()? {
+            remove_dir_all(&workspace)?;
+        }
+        let dotdev = workspace.join(".devcontainer");
+        copy_dir(&tests, &dotdev)?;
+        copy_dir(&src, &dotdev.join("src"))?;
+        scenario::write_lib(&dotdev)?;
+        scenario::rewrite_features(&mut config, &src);
+        write(dotdev.join("devcontainer.json"), serde_json::to_string_pretty(&config)?)?;
+
+        let (mut cf, context) = containerfile(inspector, &workspace, None, Lock::Update)?;
+        scenario::push_test(&mut cf, &name, dotdev.strip_prefix(&context)?);
+        let path = workspace.join("Containerfile");
+        write(&path, format!("{cf}\n"))?;
+        outcomes.push(Outcome {
+            passed: builder.build(&path, &context)?,
+            name,
+        });
+    }
+    Ok(outcomes)
+}
+
+/// Writes the configuration an image built by `build` adds up to, its metadata label merged.
+/// Features are pinned to the lockfile, which is left untouched
+pub fn config_resolved(
+    mut w: impl Write,
+    inspector: &mut impl Inspector,
+    workspace: &Path,
+    config: Option<&Path>,
+) -> Result<()> {
+    let config = Config::find_config(workspace, config)?;
+    let devc = DevContainer::new(read_to_string(&config.path)?)?;
+    writeln!(
+        w,
+        "{}",
+        serde_json::to_string_pretty(&get_merged(inspector, &config, &devc)?)?
+    )?;
+    Ok(())
+}
+
+/// Metadata of the image, of the features, pinned to the lockfile, and of the config merged
+fn get_merged(
+    inspector: &mut impl Inspector,
+    config: &Config,
+    devc: &DevContainer,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let features = resolve(config, devc, &Lockfile::load(config)?.unwrap_or_default())?;
+    let ids = install_order(&features, &devc.common.override_feature_install_order)?;
+
+    let (mut directives, mut sink) = (vec![], vec![]);
+    push_base(&mut directives, &mut sink, get_config_dir(config)?, devc, BASE_STAGE)?;
+    let mut base = BaseStage::new(&sink, BASE_STAGE);
+    // NOTE: without its config, the image's metadata is left out
+    base.inherit(inspector).ok();
+    let mut metadata = base.metadata;
+    let feats = ids
+        .iter()
+        .map(|id| (id.as_str(), &features[*id].feat))
+        .collect::<Vec<_>>();
+    metadata.extend(get_metadata(devc, &feats)?);
+    Ok(merge(&metadata))
+}
+
+/// Writes a compose override file that points the devcontainer service at the generated Containerfile
+pub fn compose_override(
+    w: impl Write,
+    inspector: &mut impl Inspector,
+    workspace: &Path,
+    config: Option<&Path>,
+    containerfile: &Path,
+) -> Result<()> {
+    let config = Config::find_config(workspace, config)?;
+    let config_dir = get_config_dir(&config)?;
+    let devc = DevContainer::new(read_to_string(&config.path)?)?;
+
+    let IsCompose::Compose(compose) = &devc.is_compose else {
+        return Err(Error::ComposeNotFound { path: config.path });
+    };
+    let service = Service::new(config_dir, compose)?;
+    let context = match &service.build {
+        Some(build) => &build.context,
+        None => config_dir,
+    };
+    // NOTE: `init` is up to the runtime, the image merely records it in its metadata
+    let init = get_merged(inspector, &config, &devc)?.get("init") == Some(&serde_json::Value::Bool(true));
+    service.write_override(w, context, &containerfile.canonicalize()?, init)
+}
+
+/// Folder holding the config, which its relative paths resolve against
+fn get_config_dir(config: &Config) -> Result<&Path> {
+    config.path.parent().ok_or_else(|| Error::ConfigDirNotFound {
+        path: config.path.clone(),
+    })
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    create_dir_all(to)?;
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        let path = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &path)?;
+        } else {
+            copy(entry.path(), path)?;
+        }
+    }
     Ok(())
 }
 
+/// What the base stage ends up with, following its parent stages up to the image they are built from
+struct BaseStage {
+    cmd: Option<Command>,
+    /// Index right after the stage's last instruction
+    end: usize,
+    entrypoint: Option<Command>,
+    /// Image at the root of the stages, with the global `ARG`s substituted, `None` for `scratch`
+    image: Option<String>,
+    /// Whether the image's entrypoint and command still apply, i.e. no stage sets ENTRYPOINT
+    inherits: bool,
+    /// Entries of the image's `devcontainer.metadata` label
+    metadata: Vec<serde_json::Map<String, serde_json::Value>>,
+    /// Index of the stage's `FROM`
+    start: usize,
+    /// `None` until a stage or the image sets it
+    user: Option<String>,
+}
+
+impl BaseStage {
+    fn new(sink: &[Instr], name: &str) -> Self {
+        let mut args = HashMap::new();
+        let mut stages = vec![];
+        for (i, instr) in sink.iter().enumerate() {
+            match instr {
+                Instr::Arg(inner) if stages.is_empty() => {
+                    for (key, value) in inner {
+                        args.insert(key.clone(), value.clone().unwrap_or_default());
+                    }
+                }
+                Instr::From { kind, name, .. } => stages.push((i, kind, name.as_deref())),
+                _ => {}
+            }
+        }
+
+        // NOTE: parents precede their children, e.g. `FROM base AS dev` or `FROM 0`
+        let mut chain = vec![];
+        let mut target = stages.iter().rposition(|(_, _, inner)| *inner == Some(name));
+        while let Some(index) = target {
+            chain.push(index);
+            target = match stages[index].1 {
+                FromKind::Stage(parent) => stages[..index]
+                    .iter()
+                    .position(|(_, _, inner)| inner.is_some_and(|inner| inner.eq_ignore_ascii_case(parent)))
+                    .or_else(|| parent.parse().ok().filter(|parent| *parent < index)),
+                _ => None,
+            };
+        }
+
+        let mut base = Self {
+            cmd: None,
+            end: sink.len(),
+            entrypoint: None,
+            image: None,
+            inherits: true,
+            metadata: vec![],
+            start: chain.first().map_or(sink.len(), |index| stages[*index].0),
+            user: None,
+        };
+        for &index in chain.iter().rev() {
+            let (from, kind, _) = stages[index];
+            if let FromKind::Image { .. } = kind {
+                let image = expand_args(&kind.to_string(), &args);
+                base.image = (image != "scratch").then_some(image);
+            }
+            let until = stages.get(index + 1).map_or(sink.len(), |(next, _, _)| *next);
+            base.end = from + 1;
+            for (i, instr) in sink.iter().enumerate().take(until).skip(from + 1) {
+                match instr {
+                    Instr::Comment(_) | Instr::Empty => continue,
+                    Instr::Cmd(command) => base.cmd = Some(command.clone()),
+                    // NOTE: setting ENTRYPOINT resets CMD
+                    Instr::Entrypoint(command) => {
+                        (base.entrypoint, base.cmd, base.inherits) = (Some(command.clone()), None, false)
+                    }
+                    Instr::User { user, .. } => base.user = Some(user.clone()),
+                    _ => {}
+                }
+                base.end = i + 1;
+            }
+        }
+        base
+    }
+
+    /// Fills in what the stages leave to the image, from its config. Root is the user an image leaves unset
+    fn inherit(&mut self, inspector: &mut impl Inspector) -> Result<()> {
+        let Some(image) = &self.image else {
+            self.user.get_or_insert_with(|| ROOT.to_string());
+            self.inherits = false;
+            return Ok(());
+        };
+        let config = inspector.inspect(image)?;
+        self.metadata = config.get_metadata()?;
+        if self.user.is_none() {
+            self.user = Some(config.user.filter(|user| !user.is_empty()).unwrap_or(ROOT.to_string()));
+        }
+        if self.inherits {
+            self.entrypoint = config.entrypoint.map(Command::Exec);
+            self.cmd = self.cmd.take().or(config.cmd.map(Command::Exec));
+            self.inherits = false;
+        }
+        Ok(())
+    }
+}
+
+/// `$NAME` and `${NAME}` replaced with the value of the `ARG`, undeclared ones are left as is
+fn expand_args(s: &str, args: &HashMap<String, String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let tail = &rest[start + 1..];
+        let (name, len) = match tail.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = tail
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(tail.len());
+                (&tail[..end], end)
+            }
+        };
+        match args.get(name) {
+            Some(value) if !name.is_empty() => expanded.push_str(value),
+            _ => expanded.push_str(&rest[start..start + 1 + len]),
+        }
+        rest = &tail[len..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// `_CONTAINER_USER` and `_REMOTE_USER`, with their homes looked up when the feature runs
+///
+/// @see: https://containers.dev/implementors/features/#user-env-var
+fn get_builtin_env(container_user: &str, remote_user: &str) -> String {
+    let mut env = String::new();
+    for (key, user) in [("_CONTAINER_USER", container_user), ("_REMOTE_USER", remote_user)] {
+        env.push_str(&format!("{key}=\"{}\"\n", escape_env(user)));
+        // NOTE: the user may not exist yet, e.g. when a feature creates it
+        env.push_str(&format!(
+            "{key}_HOME=\"$(grep \"^${{{key}}}:\" /etc/passwd | cut -d: -f6)\"\n"
+        ));
+        env.push_str(&format!("{key}_HOME=\"${{{key}_HOME:-/home/${{{key}}}}}\"\n"));
+    }
+    env
+}
+
+/// Copies the wrapper running `entrypoints` in order, then exec'ing the command, and makes it the
+/// entrypoint in front of the one the base stage declares or inherits from its image
+///
+/// @see: https://containers.dev/implementors/features/#devcontainer-feature-json-properties
+fn push_entrypoint(sink: &mut Vec<Instr>, context: &Path, entrypoints: &[&str], base: &BaseStage) -> Result<()> {
+    let mut script = String::from("#!/bin/sh\n");
+    for entrypoint in entrypoints {
+        script.push_str(&format!("{entrypoint}\n"));
+    }
+    script.push_str("exec \"$@\"\n");
+    let dir = context.join(".devpp");
+    create_dir_all(&dir)?;
+    write(dir.join("entrypoint.sh"), script)?;
+
+    sink.push(Instr::Copy {
+        destination: PathBuf::from(ENTRYPOINT),
+        heredocs: vec![],
+        options: Some(CopyOptions {
+            chmod: Some(String::from("0755")),
+            link: true,
+            ..Default::default()
+        }),
+        source: vec![Path::new(".").join(".devpp").join("entrypoint.sh")],
+    });
+    let mut entrypoint = vec![ENTRYPOINT.to_string()];
+    entrypoint.extend(base.entrypoint.as_ref().map(get_exec_form).unwrap_or_default());
+    sink.push(Instr::Entrypoint(Command::Exec(entrypoint)));
+    // NOTE: ENTRYPOINT resets CMD, carry it over
+    if let Some(cmd) = &base.cmd {
+        sink.push(Instr::Cmd(Command::Exec(get_exec_form(cmd))));
+    }
+    Ok(())
+}
+
+/// Arguments a command runs as
+fn get_exec_form(command: &Command) -> Vec<String> {
+    match command {
+        Command::Exec(args) => args.clone(),
+        Command::Shell(script) => vec![String::from("/bin/sh"), String::from("-c"), script.clone()],
+    }
+}
+
+/// Double-quoted shell value
+fn escape_env(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$")
+        .replace('`', "\\`")
+}
+
+fn push_image(sink: &mut Vec<Instr>, image: String, base_stage: &str) {
+    sink.push(Instr::From {
+        kind: FromKind::Image {
+            digest: None,
+            image,
+            repo: None,
+            tag: None,
+        },
+        name: Some(base_stage.to_string()),
+        platform: None,
+    });
+}
+
+fn get_depends_on_options(options: &serde_json::Value) -> BTreeMap<String, String> {
+    let mut opts = BTreeMap::new();
+    for (key, value) in options.as_object().into_iter().flatten() {
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        opts.insert(key.clone(), value);
+    }
+    opts
+}
+
 #[derive(Debug)]
-struct Entry<'a> {
+struct Entry {
     cstm: Customizations,
+    /// Keys of the features pulled in by `dependsOn`
+    depends_on: Vec<String>,
     feat: Feature,
-    opts: &'a BTreeMap<String, String>,
+    /// Keys of the installed features from `installsAfter`
+    installs_after: Vec<String>,
+    /// Feature id, suffixed when another feature has it, naming the stage and the folders of the feature
+    name: String,
+    /// Validated options with the defaults filled in
+    opts: BTreeMap<String, String>,
+    /// @see: [`get_source`]
+    source: String,
 }
 
-impl<'a> Entry<'a> {
+impl Entry {
+    fn get_dependencies(&self) -> impl Iterator<Item = &String> {
+        self.installs_after.iter().chain(&self.depends_on)
+    }
+
     fn get_devpp_mounts(&self) -> Vec<Mount> {
         let mut mounts = vec![];
         if let Some(devpp) = &self.cstm.0.devpp {
@@ -213,8 +976,91 @@ impl<'a> Entry<'a> {
         mounts
     }
 
+    /// Non-merged features this one transitively depends on, stopping at merge features
+    fn get_non_merged_ancestors<'a>(&'a self, features: &'a BTreeMap<String, Entry>) -> HashSet<&'a String> {
+        let mut ancestors = HashSet::new();
+        let mut stack = self.get_dependencies().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            let entry = features.get(id).expect("entry exists");
+            if entry.is_merge() || !ancestors.insert(id) {
+                continue;
+            }
+            stack.extend(entry.get_dependencies());
+        }
+        ancestors
+    }
+
     fn get_feature_id(&self) -> String {
-        format!("devpp-feature-{}", self.feat.inner.id)
+        format!("devpp-feature-{}", self.name)
+    }
+
+    /// Copies a feature that lives outside of the build context (e.g. in the cache) into `.devpp/features/<id>/`
+    fn stage(&mut self, context: &Path) -> Result<()> {
+        let dir = self
+            .feat
+            .metadata
+            .parent()
+            .expect("metadata has a parent")
+            .to_path_buf();
+        if dir.starts_with(context) {
+            return Ok(());
+        }
+
+        let staged = context.join(".devpp").join("features").join(&self.name);
+        if staged.try_exists()? {
+            remove_dir_all(&staged)?;
+        }
+        copy_dir(&dir, &staged)?;
+
+        let rebase = |path: &Path| -> Result<PathBuf> { Ok(staged.join(path.strip_prefix(&dir)?)) };
+        self.feat.entrypoint = rebase(&self.feat.entrypoint)?;
+        self.feat.merger = self.feat.merger.as_deref().map(rebase).transpose()?;
+        self.feat.metadata = rebase(&self.feat.metadata)?;
+        Ok(())
+    }
+
+    /// Where `devcontainer-features.env` is written within the build context
+    fn get_env_id(&self) -> String {
+        format!("devpp-env-{}", self.name)
+    }
+
+    /// Pushes a stage holding the options as `devcontainer-features.env` and the users as
+    /// `devcontainer-features.builtin.env`, which the feature's scripts source
+    ///
+    /// @see: https://containers.dev/implementors/features/#option-resolution
+    fn push_env(&self, sink: &mut Vec<Instr>, builtin: &str) {
+        let mut env = String::new();
+        for (key, value) in &self.opts {
+            env.push_str(&format!("{}=\"{}\"\n", env_name(key), escape_env(value)));
+        }
+        sink.push(Instr::From {
+            kind: FromKind::Image {
+                digest: None,
+                image: String::from("scratch"),
+                repo: None,
+                tag: None,
+            },
+            name: Some(self.get_env_id()),
+            platform: None,
+        });
+        let heredocs = [(BUILTIN_ENV, builtin.to_string()), (FEATURE_ENV, env)]
+            .into_iter()
+            .map(|(file, content)| Heredoc {
+                chomp: false,
+                content,
+                delimiter: file.to_string(),
+                expand: false,
+            })
+            .collect::<Vec<_>>();
+        sink.push(Instr::Copy {
+            destination: PathBuf::from("/"),
+            heredocs,
+            options: None,
+            source: [BUILTIN_ENV, FEATURE_ENV]
+                .iter()
+                .map(|file| PathBuf::from(format!("<<\"{file}\"")))
+                .collect(),
+        });
     }
 
     fn is_merge(&self) -> bool {
@@ -224,37 +1070,26 @@ impl<'a> Entry<'a> {
         }
     }
 
-    fn push_args(&self, sink: &mut Vec<Instr>) {
-        let mut args = vec![];
-
+    fn push_help(&self, sink: &mut Vec<Instr>) {
         for (key, option) in &self.feat.inner.options {
-            let (default, description) = match option {
-                FeatureOption::Variant0 { .. } => unimplemented!(),
-                FeatureOption::Variant1 {
-                    default, description, ..
-                } => (default, description),
-                FeatureOption::Variant2 {
-                    default, description, ..
-                } => (default, description),
+            let description = match option {
+                FeatureOption::Variant0 { description, .. }
+                | FeatureOption::Variant1 { description, .. }
+                | FeatureOption::Variant2 { description, .. } => description,
             };
 
             if let Some(description) = description {
                 sink.push(Instr::Comment(format!("@help({key}): {description}")));
             };
-
-            args.push((key.to_uppercase(), Some(self.opts.get(key).unwrap_or(default).clone())))
-        }
-
-        if !args.is_empty() {
-            sink.push(Instr::Arg(args))
         }
     }
 
     fn push_copy(&self, sink: &mut Vec<Instr>) {
         // TODO: unhardcode install path
-        let path = Path::new("/opt/").join(&self.feat.inner.id).join("");
+        let path = Path::new("/opt/").join(&self.name).join("");
         sink.push(Instr::Copy {
             destination: path.clone(),
+            heredocs: vec![],
             options: Some(CopyOptions {
                 from: Some(FromKind::Stage(self.get_feature_id())),
                 link: true,
@@ -292,7 +1127,7 @@ impl<'a> Entry<'a> {
     }
 
     fn push_feature(&self, sink: &mut Vec<Instr>, context: &Path) -> Result<()> {
-        self.push_args(sink);
+        self.push_help(sink);
         self.push_envs(sink);
         self.push_run(sink, context, &self.feat.entrypoint)?;
         Ok(())
@@ -304,20 +1139,38 @@ impl<'a> Entry<'a> {
         let file_name = path.file_name().unwrap();
 
         let destination = PathBuf::from("/feature/");
-        let mut mounts = vec![Mount::Bind {
-            destination: destination.clone(),
-            options: Some(BindOptions {
-                source: Some(Path::new(".").join(dir_name.strip_prefix(context)?).join("")),
-                ..Default::default()
-            }),
-        }];
+        let env = PathBuf::from("/feature-env/");
+        let mut mounts = vec![
+            Mount::Bind {
+                destination: destination.clone(),
+                options: Some(BindOptions {
+                    source: Some(Path::new(".").join(dir_name.strip_prefix(context)?).join("")),
+                    ..Default::default()
+                }),
+            },
+            Mount::Bind {
+                destination: env.clone(),
+                options: Some(BindOptions {
+                    from: Some(FromKind::Stage(self.get_env_id())),
+                    ..Default::default()
+                }),
+            },
+        ];
         mounts.extend(self.get_devpp_mounts());
 
+        // NOTE: options are sourced by each run rather than declared as ARGs, which every feature in the stage would see
         sink.push(Instr::Run {
-            command: vec![
-                String::from("sh"), // TODO: chmod 0755
+            command: Command::Exec(vec![
+                String::from("sh"),
+                String::from("-c"),
+                format!(
+                    r#"set -a && . {} && . {} && set +a && exec sh "$0""#, // TODO: chmod 0755
+                    env.join(BUILTIN_ENV).to_str().expect("UTF-8"),
+                    env.join(FEATURE_ENV).to_str().expect("UTF-8")
+                ),
                 destination.join(file_name).to_str().expect("UTF-8").to_string(),
-            ],
+            ]),
+            heredocs: vec![],
             options: Some(RunOptions {
                 mount: Some(mounts),
                 ..Default::default()
@@ -326,3 +1179,679 @@ impl<'a> Entry<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use devpp_spec::image::ImageConfig;
+
+    use super::*;
+    use crate::image::OfflineInspector;
+
+    pub(crate) fn root(path: impl AsRef<Path>) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(path)
+    }
+
+    /// Copy of the fixture, so that what a build writes stays out of the source tree
+    pub(crate) fn fixture(name: &str) -> tempfile::TempDir {
+        let workspace = tempfile::tempdir().unwrap();
+        copy_dir(&root("tests/fixtures").join(name), workspace.path()).unwrap();
+        workspace
+    }
+
+    /// Stand-in for the registries, so that the tests stay offline
+    pub(crate) struct FakeInspector;
+
+    impl Inspector for FakeInspector {
+        fn inspect(&mut self, image: &str) -> Result<ImageConfig> {
+            let args = |args: &[&str]| Some(args.iter().map(|arg| arg.to_string()).collect());
+            Ok(match image.strip_prefix("docker.io/").unwrap_or(image) {
+                "alpine:3" | "library/alpine:3" => ImageConfig {
+                    cmd: args(&["/bin/sh"]),
+                    ..Default::default()
+                },
+                "debian:13" | "library/debian:13" => ImageConfig {
+                    cmd: args(&["bash"]),
+                    ..Default::default()
+                },
+                "library/nginx:1" => ImageConfig {
+                    cmd: args(&["nginx", "-g", "daemon off;"]),
+                    entrypoint: args(&["/docker-entrypoint.sh"]),
+                    ..Default::default()
+                },
+                "jenkins/jenkins:lts" => ImageConfig {
+                    cmd: None,
+                    entrypoint: args(&["/usr/bin/tini", "--", "/usr/local/bin/jenkins.sh"]),
+                    user: Some(String::from("jenkins")),
+                    ..Default::default()
+                },
+                "mcr.microsoft.com/devcontainers/base:trixie" => ImageConfig {
+                    labels: Some(BTreeMap::from([(
+                        devpp_spec::image::METADATA_LABEL.to_string(),
+                        serde_json::json!([
+                            { "capAdd": ["SYS_PTRACE"], "containerEnv": { "A": "image", "B": "image" } },
+                            { "remoteUser": "vscode" },
+                        ])
+                        .to_string(),
+                    )])),
+                    ..Default::default()
+                },
+                image => panic!("{image:?} is not a known image"),
+            })
+        }
+    }
+
+    /// Body of `file` in the env stage of the feature named `name`
+    pub(crate) fn get_env(cf: &str, name: &str, file: &str) -> String {
+        let (_, stage) = cf.split_once(&format!("AS devpp-env-{name}\n")).expect(cf);
+        let mut body = String::new();
+        // NOTE: skips the COPY line, each body ends with its file name as the delimiter
+        for line in stage.lines().skip(1) {
+            if line == file {
+                return body;
+            }
+            if line == BUILTIN_ENV || line == FEATURE_ENV {
+                body.clear();
+            } else {
+                body.push_str(&format!("{line}\n"));
+            }
+        }
+        panic!("{cf}")
+    }
+
+    pub(crate) fn run_build(workspace: &Path) -> Result<String> {
+        let mut w = vec![];
+        build(&mut w, &mut FakeInspector, workspace, None, Lock::default())?;
+        Ok(String::from_utf8(w).expect("UTF-8"))
+    }
+
+    #[test]
+    fn compose_build() {
+        let tmp = fixture("compose_build");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("ARG VARIANT=\"13\"\n"), "{cf}");
+        assert!(
+            cf.contains("FROM docker.io/library/debian:${VARIANT} AS devpp-base\n"),
+            "{cf}"
+        );
+        assert!(cf.contains("FROM devpp-base AS devcontainer\n"), "{cf}");
+
+        let containerfile = workspace.join(".devcontainer/Containerfile");
+        let mut w = vec![];
+        compose_override(&mut w, &mut FakeInspector, workspace, None, &containerfile).unwrap();
+        let yaml = String::from_utf8(w).unwrap();
+        assert!(
+            yaml.contains(&format!("context: {}\n", workspace.canonicalize().unwrap().display())),
+            "{yaml}"
+        );
+        assert!(
+            yaml.contains(&format!("dockerfile: {}\n", containerfile.display())),
+            "{yaml}"
+        );
+        assert!(yaml.contains("target: devcontainer\n"), "{yaml}");
+        assert!(!yaml.contains("image:"), "{yaml}");
+        assert!(!yaml.contains("init:"), "{yaml}");
+    }
+
+    #[test]
+    fn compose_image() {
+        let tmp = fixture("compose_image");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("FROM docker.io/library/debian:13 AS devpp-base\n"), "{cf}");
+
+        let containerfile = workspace.join(".devcontainer/compose.yaml");
+        let mut w = vec![];
+        compose_override(&mut w, &mut FakeInspector, workspace, None, &containerfile).unwrap();
+        let yaml = String::from_utf8(w).unwrap();
+        assert!(yaml.contains("image: devpp-app\n"), "{yaml}");
+        assert!(yaml.contains("init: true\n"), "{yaml}");
+    }
+
+    #[test]
+    fn compose_override_not_compose() {
+        let tmp = fixture("dockerfile_legacy");
+        let workspace = tmp.path();
+        let containerfile = workspace.join("docker/Containerfile");
+        match compose_override(vec![], &mut FakeInspector, workspace, None, &containerfile) {
+            Err(Error::ComposeNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn depends_on() {
+        let tmp = fixture("depends_on");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        let c = cf.find("FROM devpp-base AS devpp-feature-c\n").expect(&cf);
+        let b = cf.find("FROM devpp-base AS devpp-feature-b\n").expect(&cf);
+        let a = cf.find("FROM devpp-base AS devpp-feature-a\n").expect(&cf);
+        assert!(c < b && b < a, "{cf}");
+        let env = get_env(&cf, "b", FEATURE_ENV);
+        assert_eq!(env, "VERSION=\"2\"\n");
+        assert_eq!(cf.matches("AS devpp-feature-b\n").count(), 1, "{cf}");
+    }
+
+    #[test]
+    fn depends_on_conflict() {
+        let tmp = fixture("depends_on_conflict");
+        let workspace = tmp.path();
+        match run_build(workspace) {
+            Err(Error::FeatureOptionsConflict { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn entrypoint() {
+        let tmp = fixture("entrypoint");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(
+            cf.contains(&format!(
+                "ENTRYPOINT [ \"{ENTRYPOINT}\", \"/docker-entrypoint.sh\" ]\nCMD [ \"/bin/sh\", \"-c\", \"sleep infinity\" ]\n"
+            )),
+            "{cf}"
+        );
+        let script = read_to_string(workspace.join(".devcontainer/.devpp/entrypoint.sh")).unwrap();
+        assert_eq!(
+            script,
+            "#!/bin/sh\n/usr/local/share/b-init.sh\n/usr/local/share/a-init.sh\nexec \"$@\"\n"
+        );
+    }
+
+    /// The image's entrypoint and command carry over, even though the config doesn't spell them out
+    #[test]
+    fn entrypoint_image() {
+        let tmp = fixture("entrypoint_image");
+        let cf = run_build(tmp.path()).unwrap();
+        assert!(
+            cf.contains(&format!(
+                "ENTRYPOINT [ \"{ENTRYPOINT}\", \"/docker-entrypoint.sh\" ]\nCMD [ \"nginx\", \"-g\", \"daemon off;\" ]\n"
+            )),
+            "{cf}"
+        );
+        assert!(cf.contains("USER nginx\n"), "{cf}");
+    }
+
+    /// Without the image config, the image's entrypoint and command stay in place
+    #[test]
+    fn entrypoint_image_offline() {
+        let tmp = fixture("entrypoint_image");
+        let mut w = vec![];
+        build(&mut w, &mut OfflineInspector, tmp.path(), None, Lock::default()).unwrap();
+        let cf = String::from_utf8(w).unwrap();
+        assert!(!cf.contains("ENTRYPOINT"), "{cf}");
+        assert!(!cf.contains("CMD"), "{cf}");
+        assert!(cf.contains(r#"\"entrypoint\":\"/usr/local/share/a-init.sh\""#), "{cf}");
+    }
+
+    #[test]
+    fn feature_cycle() {
+        let tmp = fixture("feature_cycle");
+        let workspace = tmp.path();
+        match run_build(workspace) {
+            Err(err @ Error::FeatureCycle { .. }) => {
+                let msg = err.to_string();
+                assert!(msg.contains("cargo-tools -> rust -> cargo-tools"), "{msg}");
+                assert!(msg.contains("features/rust/devcontainer-feature.json"), "{msg}");
+                assert!(msg.contains("features/cargo-tools/devcontainer-feature.json"), "{msg}");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn feature_options() {
+        let workspace = fixture("feature_options");
+        let cf = run_build(workspace.path()).unwrap();
+        assert!(cf.contains("# @help(install-tools): Install the tools\n"), "{cf}");
+        assert!(!cf.contains("ARG "), "{cf}");
+        assert!(
+            cf.contains("--mount=type=bind,destination=/feature-env/,from=devpp-env-foo "),
+            "{cf}"
+        );
+        assert_eq!(
+            get_env(&cf, "foo", FEATURE_ENV),
+            "INSTALL_TOOLS=\"false\"\nVERSION=\"\\\"\\$latest\\\"\"\n"
+        );
+    }
+
+    #[test]
+    fn feature_same_id() {
+        let tmp = fixture("feature_same_id");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("AS devpp-feature-foo\n"), "{cf}");
+        assert!(cf.contains("AS devpp-feature-foo-2\n"), "{cf}");
+        assert!(cf.contains("from=devpp-env-foo-2 "), "{cf}");
+        assert!(cf.contains("FROM scratch AS devpp-env-foo-2\n"), "{cf}");
+    }
+
+    #[test]
+    fn config_resolved() {
+        let mut w = vec![];
+        super::config_resolved(&mut w, &mut FakeInspector, fixture("metadata").path(), None).unwrap();
+        let resolved = serde_json::from_slice::<serde_json::Value>(&w).unwrap();
+        assert_eq!(resolved["capAdd"], serde_json::json!(["SYS_PTRACE"]));
+        assert_eq!(
+            resolved["entrypoints"],
+            serde_json::json!(["/usr/local/share/z-init.sh"])
+        );
+        assert_eq!(resolved["init"], true);
+        assert_eq!(resolved["postCreateCommands"], serde_json::json!(["a --setup"]));
+        assert_eq!(resolved["postStartCommands"], serde_json::json!(["echo started"]));
+        assert_eq!(resolved["remoteUser"], "vscode");
+    }
+
+    /// The image's metadata comes first, what the features and the config set is combined with it
+    #[test]
+    fn config_resolved_image() {
+        let mut w = vec![];
+        super::config_resolved(&mut w, &mut FakeInspector, fixture("metadata_image").path(), None).unwrap();
+        let resolved = serde_json::from_slice::<serde_json::Value>(&w).unwrap();
+        assert_eq!(resolved["capAdd"], serde_json::json!(["SYS_PTRACE", "NET_ADMIN"]));
+        assert_eq!(
+            resolved["containerEnv"],
+            serde_json::json!({ "A": "feature", "B": "config" })
+        );
+        assert_eq!(resolved["remoteUser"], "vscode");
+    }
+
+    #[test]
+    fn metadata() {
+        let cf = run_build(fixture("metadata").path()).unwrap();
+        let label = cf
+            .lines()
+            .find_map(|line| line.strip_prefix("LABEL devcontainer.metadata="))
+            .expect(&cf);
+        let metadata =
+            serde_json::from_str::<serde_json::Value>(&serde_json::from_str::<String>(label).unwrap()).unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!([
+                {
+                    "containerEnv": { "Z_HOME": "/opt/z" },
+                    "entrypoint": "/usr/local/share/z-init.sh",
+                    "id": "./features/z",
+                    "init": true,
+                    "mounts": [{ "source": "z-cache", "target": "/var/cache/z", "type": "volume" }],
+                    "privileged": true,
+                    "version": "0.0.0",
+                },
+                {
+                    "capAdd": ["SYS_PTRACE"],
+                    "id": "./features/a",
+                    "postCreateCommand": "a --setup",
+                    "securityOpt": ["seccomp=unconfined"],
+                    "version": "0.0.0",
+                },
+                {
+                    "containerUser": "root",
+                    "postStartCommand": "echo started",
+                    "remoteUser": "vscode",
+                },
+            ])
+        );
+    }
+
+    /// The label carries over the image's entries, which it replaces
+    #[test]
+    fn metadata_image() {
+        let cf = run_build(fixture("metadata_image").path()).unwrap();
+        let label = cf
+            .lines()
+            .find_map(|line| line.strip_prefix("LABEL devcontainer.metadata="))
+            .expect(&cf);
+        let metadata =
+            serde_json::from_str::<serde_json::Value>(&serde_json::from_str::<String>(label).unwrap()).unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!([
+                { "capAdd": ["SYS_PTRACE"], "containerEnv": { "A": "image", "B": "image" } },
+                { "remoteUser": "vscode" },
+                {
+                    "capAdd": ["NET_ADMIN"],
+                    "containerEnv": { "A": "feature" },
+                    "id": "./features/a",
+                    "version": "0.0.0",
+                },
+                { "containerEnv": { "B": "config" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn stage_outside_context() {
+        let workspace = tempfile::tempdir().unwrap();
+        let dotdev = workspace.path().join(".devcontainer");
+        copy_dir(
+            &root("tests/fixtures/dockerfile_target/.devcontainer/features"),
+            &dotdev.join("features"),
+        )
+        .unwrap();
+        std::fs::write(
+            dotdev.join("devcontainer.json"),
+            r#"{ "build": { "context": "../docker", "dockerfile": "../docker/Containerfile" }, "features": { "./features/foo": {} } }"#,
+        )
+        .unwrap();
+        create_dir_all(workspace.path().join("docker")).unwrap();
+        std::fs::write(workspace.path().join("docker/Containerfile"), "FROM debian:13\n").unwrap();
+
+        let cf = run_build(workspace.path()).unwrap();
+        assert!(cf.contains("source=./.devpp/features/foo/"), "{cf}");
+        assert!(workspace.path().join("docker/.devpp/features/foo/install.sh").exists());
+    }
+
+    #[test]
+    fn stage_levels() {
+        let tmp = fixture("depends_on");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(
+            cf.contains("# @critical-path: devpp-feature-c -> devpp-feature-b -> devpp-feature-a\n"),
+            "{cf}"
+        );
+        assert!(cf.contains("# @level: 0\nFROM devpp-base AS devpp-feature-c\n"), "{cf}");
+        assert!(cf.contains("# @level: 2\nFROM devpp-base AS devpp-feature-a\n"), "{cf}");
+    }
+
+    #[test]
+    fn install_order() {
+        let tmp = fixture("install_order");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        let b = cf.rfind("COPY --from=devpp-feature-b ").expect(&cf);
+        let c = cf.rfind("COPY --from=devpp-feature-c ").expect(&cf);
+        let a = cf.rfind("COPY --from=devpp-feature-a ").expect(&cf);
+        assert!(b < c && c < a, "{cf}");
+    }
+
+    #[test]
+    fn install_order_violation() {
+        let tmp = fixture("install_order_violation");
+        let workspace = tmp.path();
+        match run_build(workspace) {
+            Err(Error::InstallOrderViolation { dependency, id }) => {
+                assert_eq!(dependency, "./features/b");
+                assert_eq!(id, "./features/a");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    /// The override separates `a` from `b` it depends on, `c` in between depends on nothing
+    #[test]
+    fn install_order_violation_gap() {
+        let tmp = fixture("install_order_violation_gap");
+        match run_build(tmp.path()) {
+            Err(Error::InstallOrderViolation { dependency, id }) => {
+                assert_eq!(dependency, "./features/b");
+                assert_eq!(id, "./features/a");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn installs_after_dangling() {
+        let tmp = fixture("installs_after_dangling");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("FROM devpp-base AS devpp-feature-a\n"), "{cf}");
+        assert!(!cf.contains("missing"), "{cf}");
+    }
+
+    #[test]
+    fn lockfile_mismatch() {
+        let workspace = fixture("lockfile_mismatch");
+        match build(
+            std::io::sink(),
+            &mut FakeInspector,
+            workspace.path(),
+            None,
+            Lock::Frozen,
+        ) {
+            Err(Error::LockfileMismatch { id, .. }) => assert_eq!(id, "ghcr.io/devcontainers/features/node:1"),
+            other => panic!("{other:?}"),
+        }
+        match lock_update(workspace.path(), None, Some("ghcr.io/devcontainers/features/go")) {
+            Err(Error::LockfileFeatureNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+
+        lock_update(workspace.path(), None, Some("ghcr.io/devcontainers/features/node")).unwrap();
+        build(
+            std::io::sink(),
+            &mut FakeInspector,
+            workspace.path(),
+            None,
+            Lock::Frozen,
+        )
+        .unwrap();
+    }
+
+    /// `lock update` resolves the tag again, rather than the revision it was cached under
+    #[cfg(feature = "git")]
+    #[test]
+    fn lock_update_moved() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repository = tmp.path().join("features");
+        create_dir_all(repository.join("foo")).unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repository)
+                .args(["-c", "user.name=devpp", "-c", "user.email=devpp@localhost"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?}: {output:?}");
+        };
+        git(&["init", "--quiet"]);
+        let release = |version: &str| {
+            write(
+                repository.join("foo/devcontainer-feature.json"),
+                format!(r#"{{"id":"foo","version":"{version}"}}"#),
+            )
+            .unwrap();
+            write(repository.join("foo/install.sh"), "#!/bin/sh\n").unwrap();
+            git(&["add", "."]);
+            git(&["commit", "--quiet", "-m", version]);
+            git(&["tag", "--force", "stable"]);
+        };
+
+        let workspace = tmp.path().join("app");
+        create_dir_all(workspace.join(".devcontainer")).unwrap();
+        write(
+            workspace.join(".devcontainer/devcontainer.json"),
+            format!(
+                r#"{{"features":{{"git+file://{}#rev=stable&path=foo":{{}}}},"image":"docker.io/library/debian:13"}}"#,
+                repository.display()
+            ),
+        )
+        .unwrap();
+        let lockfile = workspace.join(".devcontainer/devcontainer-lock.json");
+
+        release("1.0.0");
+        lock_update(&workspace, None, None).unwrap();
+        let locked = read_to_string(&lockfile).unwrap();
+        assert!(locked.contains(r#""version": "1.0.0""#), "{locked}");
+
+        release("2.0.0");
+        lock_update(&workspace, None, None).unwrap();
+        let locked = read_to_string(&lockfile).unwrap();
+        assert!(locked.contains(r#""version": "2.0.0""#), "{locked}");
+        build(std::io::sink(), &mut FakeInspector, &workspace, None, Lock::Frozen).unwrap();
+    }
+
+    #[test]
+    fn users() {
+        let tmp = fixture("users");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("AS devpp-feature-foo\nUSER root\n"), "{cf}");
+        assert_eq!(cf.matches("USER ").count(), 2, "{cf}");
+
+        let env = get_env(&cf, "foo", BUILTIN_ENV);
+        assert!(env.contains("_CONTAINER_USER=\"vscode\"\n"), "{env}");
+        assert!(env.contains("_REMOTE_USER=\"dev\"\n"), "{env}");
+        assert!(env.contains("_REMOTE_USER_HOME=\"$(grep "), "{env}");
+    }
+
+    #[test]
+    fn users_image() {
+        let tmp = fixture("users_image");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("AS devpp-base\nUSER root\n"), "{cf}");
+        assert!(cf.contains("USER jenkins\n\nLABEL "), "{cf}");
+
+        let env = get_env(&cf, "foo", BUILTIN_ENV);
+        assert!(env.contains("_CONTAINER_USER=\"jenkins\"\n"), "{env}");
+        assert!(env.contains("_REMOTE_USER=\"jenkins\"\n"), "{env}");
+    }
+
+    /// Without the image config, the features install as the user the image leaves them
+    #[test]
+    fn users_image_offline() {
+        let tmp = fixture("users_image");
+        let workspace = tmp.path();
+        let mut w = vec![];
+        build(&mut w, &mut OfflineInspector, workspace, None, Lock::default()).unwrap();
+        let cf = String::from_utf8(w).unwrap();
+        assert!(
+            cf.contains("# @warning: the config of \"docker.io/jenkins/jenkins:lts\""),
+            "{cf}"
+        );
+        assert!(!cf.contains("USER "), "{cf}");
+
+        let env = get_env(&cf, "foo", BUILTIN_ENV);
+        assert!(env.contains("_CONTAINER_USER=\"root\"\n"), "{env}");
+    }
+
+    #[test]
+    fn users_stages() {
+        let tmp = fixture("users_stages");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("RUN mkdir -p ~/.config\nUSER root\n"), "{cf}");
+        assert!(cf.contains("USER vscode\n\nLABEL "), "{cf}");
+
+        let env = get_env(&cf, "foo", BUILTIN_ENV);
+        assert!(env.contains("_CONTAINER_USER=\"vscode\"\n"), "{env}");
+        assert!(env.contains("_REMOTE_USER=\"vscode\"\n"), "{env}");
+    }
+
+    #[test]
+    fn test_feature() {
+        /// Passes every scenario but `broken`, keeping the Containerfiles it was handed
+        #[derive(Default)]
+        struct FakeBuilder(Vec<String>);
+
+        impl Builder for FakeBuilder {
+            fn build(&mut self, containerfile: &Path, context: &Path) -> Result<bool> {
+                assert!(context.join("dev-container-features-test-lib").exists());
+                let cf = read_to_string(containerfile)?;
+                let passed = !cf.contains("broken.sh");
+                self.0.push(cf);
+                Ok(passed)
+            }
+        }
+
+        let collection = fixture("feature_test");
+        let mut builder = FakeBuilder::default();
+        let outcomes =
+            super::test_feature(&mut builder, &mut FakeInspector, collection.path(), "foo", "debian:13").unwrap();
+        let outcomes = outcomes
+            .iter()
+            .map(|outcome| (outcome.name.as_str(), outcome.passed))
+            .collect::<Vec<_>>();
+        assert_eq!(outcomes, [("test", true), ("broken", false), ("pinned", true)]);
+
+        let cf = &builder.0[0];
+        assert!(cf.contains("FROM debian:13 AS devpp-base\n"), "{cf}");
+        assert!(cf.contains("FROM devpp-base AS devpp-test-target\n"), "{cf}");
+        assert!(cf.contains("FROM devpp-test-target AS devpp-test\n"), "{cf}");
+        assert!(cf.contains("test.sh"), "{cf}");
+        let cf = &builder.0[2];
+        assert!(cf.contains("FROM devpp-base AS devpp-feature-bar\n"), "{cf}");
+        assert_eq!(get_env(cf, "foo", FEATURE_ENV), "VERSION=\"2\"\n");
+    }
+
+    #[test]
+    fn vendor_stale() {
+        let tmp = fixture("vendor_stale");
+        let workspace = tmp.path();
+        match vendor_check(workspace, None) {
+            Err(Error::VendorStale { stale }) => {
+                let references = stale.iter().map(|stale| stale.reference.as_str()).collect::<Vec<_>>();
+                assert_eq!(references, ["ghcr.io/devcontainers/features/foo:1"]);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        // NOTE: vendoring prunes the lockfile to the features still in use, orphaning both copies
+        let workspace = fixture("vendor_stale");
+        let mut w = vec![];
+        let stale = vendor(&mut w, workspace.path(), None, None).unwrap();
+        assert_eq!(stale.len(), 2);
+        let config = String::from_utf8(w).unwrap();
+        assert!(config.contains(r#""./features/foo": {}"#), "{config}");
+    }
+
+    #[test]
+    fn merge_dependencies() {
+        let tmp = fixture("merge_dependencies");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        let locale = cf.find("source=./features/locale/").expect(&cf);
+        let utils = cf.find("source=./features/utils/").expect(&cf);
+        assert!(locale < utils, "{cf}");
+
+        let cut = cf.find("FROM devpp-base AS devpp-base-1\n").expect(&cf);
+        let tool = cf.find("COPY --from=devpp-feature-tool").expect(&cf);
+        let shell = cf.find("source=./features/shell/").expect(&cf);
+        assert!(locale < cut && cut < tool && tool < shell, "{cf}");
+        assert!(cf.contains("FROM devpp-base AS devpp-feature-tool\n"), "{cf}");
+        assert!(cf.contains("FROM devpp-base-1\n"), "{cf}");
+        assert_eq!(cf.matches("COPY --from=devpp-feature-tool").count(), 1, "{cf}");
+    }
+
+    #[test]
+    fn dockerfile_legacy() {
+        let tmp = fixture("dockerfile_legacy");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.contains("ARG VARIANT=\"13\"\n"), "{cf}");
+        assert!(cf.contains("FROM debian:${VARIANT} AS devpp-base\n"), "{cf}");
+        assert!(cf.contains("ARG USERNAME=\"vscode\"\n"), "{cf}");
+        assert!(cf.contains("FROM devpp-base AS devcontainer\n"), "{cf}");
+    }
+
+    #[test]
+    fn dockerfile_target() {
+        let tmp = fixture("dockerfile_target");
+        let workspace = tmp.path();
+        let cf = run_build(workspace).unwrap();
+        assert!(cf.starts_with("# syntax=docker/dockerfile:1\n"), "{cf}");
+        assert!(cf.contains("FROM docker.io/library/debian:13 AS devpp-base\n"), "{cf}");
+        assert!(cf.contains("FROM devpp-base AS runner\n"), "{cf}");
+        assert!(
+            cf.contains("COPY --from=devpp-base [ \"/etc/os-release\", \"/etc/os-release\" ]\n"),
+            "{cf}"
+        );
+        assert!(cf.contains("FROM devpp-base AS devcontainer\n"), "{cf}");
+    }
+
+    #[test]
+    fn dockerfile_target_not_found() {
+        let tmp = fixture("dockerfile_target_not_found");
+        let workspace = tmp.path();
+        match run_build(workspace) {
+            Err(Error::TargetNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+}
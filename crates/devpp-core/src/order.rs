@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use stable_topo_sort::Dangling;
+use stable_topo_sort::critical_path;
+use stable_topo_sort::stable_topo_levels;
+use stable_topo_sort::stable_topo_sort;
+
+use crate::Entry;
+use crate::error::CycleEdge;
+use crate::error::Error;
+use crate::error::Result;
+
+/// @see: https://containers.dev/implementors/features/#installation-order
+pub(crate) fn install_order<'a>(
+    features: &'a BTreeMap<String, Entry>,
+    overrides: &[String],
+) -> Result<Vec<&'a String>> {
+    // @see: https://containers.dev/implementors/features/#overridefeatureinstallorder
+    let mut priority = vec![];
+    for id in overrides {
+        let key = find_key(features.keys(), id).ok_or_else(|| Error::InstallOrderNotFound { id: id.clone() })?;
+        if !priority.contains(&key) {
+            priority.push(key);
+        }
+    }
+    let rank = |id: &String| priority.iter().position(|key| *key == id);
+
+    let mut nodes = priority.clone();
+    nodes.extend(features.keys().filter(|key| rank(key).is_none()));
+
+    let mut edges = vec![];
+    for (id, entry) in features {
+        // NOTE: installsAfter is a soft dependency, so the user-specified order takes precedence
+        for dep_id in &entry.installs_after {
+            if let Some(rank_id) = rank(id)
+                && rank(dep_id).is_none_or(|rank_dep| rank_dep > rank_id)
+            {
+                continue;
+            }
+            edges.push((dep_id, id));
+        }
+        for dep_id in &entry.depends_on {
+            edges.push((dep_id, id));
+        }
+    }
+    let ids = stable_topo_sort(&nodes, &edges, Dangling::Error).map_err(|err| cycle_error(features, err))?;
+
+    // NOTE: dependsOn is a hard dependency, the user-specified order can't place a feature before its dependencies
+    for (rank_id, id) in priority.iter().enumerate() {
+        let mut stack = features[*id].depends_on.iter().collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        while let Some(dep_id) = stack.pop() {
+            if !seen.insert(dep_id) {
+                continue;
+            }
+            if rank(dep_id).is_some_and(|rank_dep| rank_dep > rank_id) {
+                return Err(Error::InstallOrderViolation {
+                    dependency: dep_id.clone(),
+                    id: id.to_string(),
+                });
+            }
+            stack.extend(&features[dep_id].depends_on);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Feature stages grouped into levels that can be built concurrently, and the longest chain of stages
+pub(crate) fn stage_levels<'a>(
+    features: &'a BTreeMap<String, Entry>,
+    ids: &[&'a String],
+) -> Result<(Vec<Vec<&'a String>>, Vec<&'a String>)> {
+    let is_stage = |id: &String| features.get(id).is_some_and(|entry| !entry.is_merge());
+
+    let nodes = ids.iter().copied().filter(|id| is_stage(id)).collect::<Vec<_>>();
+    let mut edges = vec![];
+    for id in &nodes {
+        for dep_id in features[*id].get_dependencies().filter(|dep_id| is_stage(dep_id)) {
+            edges.push((dep_id, *id));
+        }
+    }
+
+    let levels = stable_topo_levels(&nodes, &edges, Dangling::Error).map_err(|err| cycle_error(features, err))?;
+    let path = critical_path(&nodes, &edges, Dangling::Error).map_err(|err| cycle_error(features, err))?;
+    Ok((levels, path))
+}
+
+fn cycle_error(features: &BTreeMap<String, Entry>, err: stable_topo_sort::error::Error<&String>) -> Error {
+    match err {
+        stable_topo_sort::error::Error::CycleDetected { cycle } => Error::FeatureCycle {
+            cycle: cycle
+                .windows(2)
+                .map(|pair| {
+                    let (from, to) = (&features[pair[0]], &features[pair[1]]);
+                    CycleEdge {
+                        from: from.feat.inner.id.clone(),
+                        metadata: to.feat.metadata.clone(),
+                        to: to.feat.inner.id.clone(),
+                    }
+                })
+                .collect(),
+        },
+        stable_topo_sort::error::Error::DanglingEdge { .. } => unreachable!("edges are resolved against the features"),
+    }
+}
+
+/// Installed feature key referenced by `id`, which may omit the version
+pub(crate) fn find_key<'a>(keys: impl IntoIterator<Item = &'a String>, id: &str) -> Option<&'a String> {
+    keys.into_iter().find(|key| *key == id || strip_version(key) == id)
+}
+
+/// Feature identifier without its tag or digest
+fn strip_version(id: &str) -> &str {
+    if let Some((id, _digest)) = id.split_once('@') {
+        return id;
+    }
+    match id.rsplit_once(':') {
+        Some((id, tag)) if !tag.contains('/') => id,
+        _ => id,
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command as Process;
+
+use containerfile::Containerfile;
+use containerfile::instr::Instr;
+use containerfile::instr::command::Command;
+use containerfile::instr::from::FromKind;
+use containerfile::instr::run::RunOptions;
+use containerfile::instr::run::mount::Mount;
+use containerfile::instr::run::mount::bind::BindOptions;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Scenario built from `test/<feature>/test.sh`, with the feature's default options
+pub const DEFAULT_SCENARIO: &str = "test";
+
+/// Stage of the test Containerfile that runs the scenario script
+pub const TEST_STAGE: &str = "devpp-test";
+
+/// Where the scenario folder is mounted while its script runs
+const TEST_DIR: &str = "/devpp-test/";
+
+/// Helpers the scripts `source`, compatible with the ones `devcontainer features test` provides
+///
+/// @see: https://github.com/devcontainers/cli/blob/main/docs/features/test.md#dev-container-features-test-lib
+const TEST_LIB: &str = r#"#!/bin/bash
+FAILED=()
+
+check() {
+    LABEL=$1
+    shift
+    echo -e "\n🧪 Testing $LABEL"
+    if "$@"; then
+        echo "✅  Passed '$LABEL'!"
+        return 0
+    fi
+    echo "❌ $LABEL check failed."
+    FAILED+=("$LABEL")
+    return 1
+}
+
+reportResults() {
+    if [ ${#FAILED[@]} -ne 0 ]; then
+        echo -e "\n💥  Failed tests: ${FAILED[*]}"
+        exit 1
+    fi
+    echo -e "\n💯  All passed!"
+    exit 0
+}
+"#;
+
+/// Builds a test Containerfile, e.g. `docker build`
+pub trait Builder {
+    /// Whether the image built, which is whether the scenario script passed
+    fn build(&mut self, containerfile: &Path, context: &Path) -> Result<bool>;
+}
+
+/// Runs `<program> <args>... -f <containerfile> <context>`
+pub struct CommandBuilder {
+    args: Vec<String>,
+    program: String,
+}
+
+impl CommandBuilder {
+    /// Splits `command` on whitespace, e.g. `podman build --layers`
+    pub fn new(command: &str) -> Self {
+        let mut words = command.split_whitespace().map(String::from);
+        Self {
+            program: words.next().unwrap_or_default(),
+            args: words.collect(),
+        }
+    }
+}
+
+impl Builder for CommandBuilder {
+    fn build(&mut self, containerfile: &Path, context: &Path) -> Result<bool> {
+        let status = Process::new(&self.program)
+            .args(&self.args)
+            .arg("-f")
+            .arg(containerfile)
+            .arg(context)
+            .status()?;
+        Ok(status.success())
+    }
+}
+
+/// Result of a single scenario
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub name: String,
+    pub passed: bool,
+}
+
+/// devcontainer.json of every scenario of `feature`, keyed by scenario name, the default scenario first
+///
+/// @see: https://github.com/devcontainers/cli/blob/main/docs/features/test.md#scenarios
+pub(crate) fn load(tests: &Path, feature: &str, base_image: &str) -> Result<Vec<(String, Value)>> {
+    let mut scenarios = vec![];
+    if tests.join(format!("{DEFAULT_SCENARIO}.sh")).try_exists()? {
+        let config = serde_json::json!({
+            "image": base_image,
+            "features": { feature: {} },
+        });
+        scenarios.push((String::from(DEFAULT_SCENARIO), config));
+    }
+    let path = tests.join("scenarios.json");
+    if path.try_exists()? {
+        let mut s = std::fs::read_to_string(&path)?;
+        json_strip_comments::strip(&mut s)?;
+        scenarios.extend(serde_json::from_str::<BTreeMap<String, Value>>(&s)?);
+    }
+    if scenarios.is_empty() {
+        return Err(Error::TestNotFound {
+            feature: feature.to_string(),
+            path: tests.to_path_buf(),
+        });
+    }
+    for (name, _) in &scenarios {
+        let script = tests.join(format!("{name}.sh"));
+        if !script.try_exists()? {
+            return Err(Error::TestScriptNotFound { path: script });
+        }
+    }
+    Ok(scenarios)
+}
+
+/// Points the features of the collection at their copies in `./src/`
+pub(crate) fn rewrite_features(config: &mut Value, src: &Path) {
+    if let Some(map) = config.get_mut("features").and_then(Value::as_object_mut) {
+        *map = std::mem::take(map)
+            .into_iter()
+            .map(|(id, options)| {
+                let local = src.join(&id).join("devcontainer-feature.json").exists();
+                (if local { format!("./src/{id}") } else { id }, options)
+            })
+            .collect();
+    }
+}
+
+/// Writes the helpers next to the scenario scripts
+pub(crate) fn write_lib(dir: &Path) -> Result<()> {
+    std::fs::write(dir.join("dev-container-features-test-lib"), TEST_LIB)?;
+    Ok(())
+}
+
+/// Appends a stage on top of the final image that runs the scenario script, `source` is the
+/// folder holding the scripts relative to the build context
+pub(crate) fn push_test(cf: &mut Containerfile, name: &str, source: &Path) {
+    let target = cf
+        .iter_mut()
+        .rev()
+        .find_map(|instr| match instr {
+            Instr::From { name, .. } => Some(name.get_or_insert_with(|| format!("{TEST_STAGE}-target")).clone()),
+            _ => None,
+        })
+        .expect("containerfile declares a stage");
+
+    cf.push(Instr::Empty);
+    cf.push(Instr::From {
+        kind: FromKind::Stage(target),
+        name: Some(TEST_STAGE.to_string()),
+        platform: None,
+    });
+    cf.push(Instr::Empty);
+    cf.push(Instr::Workdir(Path::new(TEST_DIR).to_path_buf()));
+    // NOTE: the mount is writable, so that scripts can be made executable without touching the sources
+    cf.push(Instr::Run {
+        command: Command::Exec(vec![
+            String::from("sh"),
+            String::from("-c"),
+            String::from(r#"chmod +x "./$0" && exec "./$0""#),
+            format!("{name}.sh"),
+        ]),
+        heredocs: vec![],
+        options: Some(RunOptions {
+            mount: Some(vec![Mount::Bind {
+                destination: Path::new(TEST_DIR).to_path_buf(),
+                options: Some(BindOptions {
+                    readwrite: true,
+                    source: Some(Path::new(".").join(source).join("")),
+                    ..Default::default()
+                }),
+            }]),
+            ..Default::default()
+        }),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy_dir;
+    use crate::tests::root;
+
+    #[test]
+    fn load_script_not_found() {
+        let tests = tempfile::tempdir().unwrap();
+        copy_dir(&root("tests/fixtures/feature_test/test/foo"), tests.path()).unwrap();
+        std::fs::remove_file(tests.path().join("pinned.sh")).unwrap();
+        match load(tests.path(), "foo", "debian:13") {
+            Err(Error::TestScriptNotFound { path }) => assert!(path.ends_with("pinned.sh")),
+            other => panic!("{other:?}"),
+        }
+        match load(&tests.path().join("missing"), "foo", "debian:13") {
+            Err(Error::TestNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+}
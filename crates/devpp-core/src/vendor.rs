@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::fs::remove_dir_all;
+use std::fs::write;
+use std::path::Component;
+use std::path::Path;
+
+use devpp_spec::lock::Locked;
+use devpp_spec::lock::Lockfile;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Entry;
+use crate::copy_dir;
+use crate::error::Result;
+use crate::error::Stale;
+use crate::order::find_key;
+
+/// Left in every vendored feature, to tell which reference it was copied from
+const MARKER: &str = ".devpp-vendor.json";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Marker {
+    #[serde(flatten)]
+    locked: Locked,
+    reference: String,
+}
+
+/// Copies the feature into `dest`, pointing its references at the vendored copies listed in `locals`
+pub(crate) fn copy_feature(
+    entry: &Entry,
+    reference: &str,
+    dest: &Path,
+    locals: &BTreeMap<String, String>,
+) -> Result<()> {
+    let Some(locked) = &entry.feat.locked else {
+        return Ok(());
+    };
+    if dest.try_exists()? {
+        remove_dir_all(dest)?;
+    }
+    copy_dir(entry.feat.metadata.parent().expect("metadata has a parent"), dest)?;
+
+    let path = dest.join("devcontainer-feature.json");
+    let mut s = read_to_string(&path)?;
+    json_strip_comments::strip(&mut s)?;
+    let mut value = serde_json::from_str::<Value>(&s)?;
+    rewrite_references(&mut value, locals);
+    write(&path, serde_json::to_string_pretty(&value)? + "\n")?;
+
+    let marker = Marker {
+        locked: locked.clone(),
+        reference: reference.to_string(),
+    };
+    write(dest.join(MARKER), serde_json::to_string_pretty(&marker)? + "\n")?;
+    Ok(())
+}
+
+/// Points `features`, `dependsOn` and `installsAfter` at the vendored copies
+pub(crate) fn rewrite_references(value: &mut Value, locals: &BTreeMap<String, String>) {
+    let local = |id: &String| find_key(locals.keys(), id).map(|key| locals[key].clone());
+    for field in ["dependsOn", "features"] {
+        if let Some(map) = value.get_mut(field).and_then(Value::as_object_mut) {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(id, options)| (local(&id).unwrap_or(id), options))
+                .collect();
+        }
+    }
+    if let Some(ids) = value.get_mut("installsAfter").and_then(Value::as_array_mut) {
+        for id in ids {
+            if let Some(path) = id.as_str().map(String::from).as_ref().and_then(local) {
+                *id = Value::String(path);
+            }
+        }
+    }
+}
+
+/// Vendored copies whose origin is no longer pinned by the lockfile
+pub(crate) fn find_stale(vendor: &Path, lockfile: &Lockfile) -> Result<Vec<Stale>> {
+    let mut stale = vec![];
+    if !vendor.try_exists()? {
+        return Ok(stale);
+    }
+    for entry in read_dir(vendor)? {
+        let path = entry?.path().join(MARKER);
+        if !path.try_exists()? {
+            continue;
+        }
+        let marker = serde_json::from_str::<Marker>(&read_to_string(&path)?)?;
+        if lockfile.features.get(&marker.reference) != Some(&marker.locked) {
+            stale.push(Stale {
+                path: path.parent().expect("marker has a parent").to_path_buf(),
+                reference: marker.reference,
+            });
+        }
+    }
+    stale.sort_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+    Ok(stale)
+}
+
+/// Feature reference to `to` from a config in `from`, both must be absolute
+pub(crate) fn relative(from: &Path, to: &Path) -> String {
+    let (from, to) = (
+        from.components().collect::<Vec<_>>(),
+        to.components().collect::<Vec<_>>(),
+    );
+    let common = from.iter().zip(&to).take_while(|(lhs, rhs)| lhs == rhs).count();
+    let mut path = match common == from.len() {
+        true => vec![String::from(".")],
+        false => vec![String::from(".."); from.len() - common],
+    };
+    path.extend(to[common..].iter().map(|component| match component {
+        Component::Normal(name) => name.to_string_lossy().into_owned(),
+        _ => unreachable!("paths are absolute"),
+    }));
+    path.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn relative() {
+        let to = Path::new("/ws/.devcontainer/vendor/foo");
+        assert_eq!(super::relative(Path::new("/ws/.devcontainer"), to), "./vendor/foo");
+        assert_eq!(
+            super::relative(Path::new("/ws/.devcontainer/airgap"), to),
+            "../vendor/foo"
+        );
+    }
+
+    #[test]
+    fn rewrite_references() {
+        let locals = BTreeMap::from([(
+            String::from("ghcr.io/devcontainers/features/common-utils:2"),
+            String::from("./vendor/common-utils"),
+        )]);
+        let mut value = json!({
+            "dependsOn": { "ghcr.io/devcontainers/features/common-utils:2": {}, "./features/bar": {} },
+            "installsAfter": ["ghcr.io/devcontainers/features/common-utils", "ghcr.io/devcontainers/features/git"],
+        });
+        super::rewrite_references(&mut value, &locals);
+        assert_eq!(
+            value,
+            json!({
+                "dependsOn": { "./vendor/common-utils": {}, "./features/bar": {} },
+                "installsAfter": ["./vendor/common-utils", "ghcr.io/devcontainers/features/git"],
+            })
+        );
+    }
+}
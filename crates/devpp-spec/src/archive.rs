@@ -0,0 +1,128 @@
+use std::fs::create_dir_all;
+use std::fs::remove_dir_all;
+use std::fs::rename;
+use std::io::Read;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Extracts a tar stream into `cache/name` and returns that directory
+pub(crate) fn unpack(reader: impl Read, cache: &Path, name: &str) -> Result<PathBuf> {
+    let dir = cache.join(name);
+    // NOTE: extract next to the destination and move it into place, so that readers never see partial results
+    create_dir_all(cache)?;
+    let tmp = cache.join(format!(".{name}.tmp"));
+    if tmp.try_exists()? {
+        remove_dir_all(&tmp)?;
+    }
+    create_dir_all(&tmp)?;
+    if let Err(err) = unpack_in(reader, &tmp) {
+        remove_dir_all(&tmp)?;
+        return Err(err);
+    }
+    rename(&tmp, &dir)?;
+    Ok(dir)
+}
+
+fn unpack_in(reader: impl Read, dir: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        // NOTE: `tar` silently skips entries with `..` components, so reject them rather than drop files
+        if !is_contained(&path) || path.components().any(|component| component == Component::ParentDir) {
+            return Err(Error::ArchivePathIllegal { path });
+        }
+        if let Some(link) = entry.link_name()? {
+            // NOTE: symlink targets are relative to the link itself, hard link targets to the archive root
+            let target = match entry.header().entry_type() {
+                tar::EntryType::Symlink => path.parent().unwrap_or(Path::new("")).join(&link),
+                _ => link.into_owned(),
+            };
+            if !is_contained(&target) {
+                return Err(Error::ArchivePathIllegal { path });
+            }
+        }
+        entry.unpack_in(dir)?;
+    }
+    Ok(())
+}
+
+/// Whether a relative path stays within the directory it is resolved against
+fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::Prefix(_) | Component::RootDir => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(entries: &[(&str, tar::EntryType, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, kind, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*kind);
+            header.set_mode(0o644);
+            // NOTE: bypass the builder's path validation to produce malicious archives
+            let name = &mut header.as_old_mut().name;
+            name[..path.len()].copy_from_slice(path.as_bytes());
+            if kind.is_symlink() || kind.is_hard_link() {
+                header.set_link_name(data).unwrap();
+                header.set_size(0);
+                header.set_cksum();
+                builder.append(&header, std::io::empty()).unwrap();
+            } else {
+                header.set_size(data.len() as u64);
+                header.set_cksum();
+                builder.append(&header, data.as_bytes()).unwrap();
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unpack() {
+        let cache = tempfile::tempdir().unwrap();
+        let data = archive(&[
+            ("./install.sh", tar::EntryType::Regular, "#!/bin/sh\n"),
+            ("lib/link", tar::EntryType::Symlink, "../install.sh"),
+        ]);
+        let dir = super::unpack(data.as_slice(), cache.path(), "foo").unwrap();
+        assert_eq!(dir, cache.path().join("foo"));
+        assert!(dir.join("install.sh").exists());
+        assert!(dir.join("lib").join("link").exists());
+    }
+
+    #[test]
+    fn unpack_path_illegal() {
+        for entries in [
+            [("../escape", tar::EntryType::Regular, "")],
+            [("/escape", tar::EntryType::Regular, "")],
+            [("a/../../escape", tar::EntryType::Regular, "")],
+            [("link", tar::EntryType::Symlink, "../escape")],
+            [("link", tar::EntryType::Symlink, "/etc/passwd")],
+            [("link", tar::EntryType::Link, "a/../../escape")],
+        ] {
+            let cache = tempfile::tempdir().unwrap();
+            match super::unpack(archive(&entries).as_slice(), &cache.path().join("cache"), "foo") {
+                Err(Error::ArchivePathIllegal { .. }) => {}
+                other => panic!("{entries:?}: {other:?}"),
+            }
+            assert!(!cache.path().join("escape").exists());
+            assert!(!cache.path().join("cache").join("foo").exists());
+            assert!(!cache.path().join("cache").join(".foo.tmp").exists());
+        }
+    }
+}
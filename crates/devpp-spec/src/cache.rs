@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::fs::remove_dir_all;
+use std::fs::rename;
+use std::fs::write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Root of the devpp cache: `$DEVPP_CACHE_DIR`, `$XDG_CACHE_HOME/devpp` or `~/.cache/devpp`
+///
+/// @see: https://specifications.freedesktop.org/basedir-spec/latest/
+pub fn dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("DEVPP_CACHE_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir).join("devpp"));
+    }
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".cache").join("devpp")),
+        None => Err(Error::CacheNotFound),
+    }
+}
+
+/// Remote features shared across projects
+///
+/// Contents live in `blobs/<digest>/`, `index.json` maps immutable references, such as digests and commits,
+/// onto the digest of their contents. Mutable references are resolved again on every fetch.
+pub struct Cache {
+    root: PathBuf,
+}
+
+/// @see: [`Cache`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Record {
+    /// Directory name under `blobs/`
+    pub digest: String,
+    /// Seconds since the Unix epoch
+    pub fetched: u64,
+    /// Immutable reference the contents were fetched from
+    pub resolved: String,
+}
+
+impl Record {
+    /// Digest in the `<algorithm>:<hex>` form of lockfiles
+    pub fn integrity(&self) -> String {
+        self.digest.replacen('-', ":", 1)
+    }
+}
+
+impl Cache {
+    pub fn new() -> Result<Self> {
+        Ok(Self::at(dir()?))
+    }
+
+    pub fn at(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn blobs(&self) -> PathBuf {
+        self.root.join("blobs")
+    }
+
+    /// Mirrors of git repositories, so that only new revisions are fetched
+    pub fn git(&self) -> PathBuf {
+        self.root.join("git")
+    }
+
+    /// Cached contents of the immutable `reference`, `fetch` extracts them into the given directory on a miss
+    /// and returns them along with the immutable reference they were resolved to
+    pub fn fetch(
+        &self,
+        reference: &str,
+        fetch: impl FnOnce(&Path) -> Result<(PathBuf, String)>,
+    ) -> Result<(PathBuf, Record)> {
+        if let Some(record) = self.list()?.get(reference) {
+            let path = self.blobs().join(&record.digest);
+            if path.try_exists()? {
+                return Ok((path, record.clone()));
+            }
+        }
+        self.insert(fetch, |_| reference.to_string())
+    }
+
+    /// Contents of a mutable reference, such as a tag or a branch, which are fetched every time since the
+    /// reference may have moved, then indexed under the immutable reference `key` derives from the record
+    ///
+    /// Contents already in `blobs/` are reused by `fetch`, so only the resolution hits the network.
+    pub fn refresh(
+        &self,
+        fetch: impl FnOnce(&Path) -> Result<(PathBuf, String)>,
+        key: impl FnOnce(&Record) -> String,
+    ) -> Result<(PathBuf, Record)> {
+        self.insert(fetch, key)
+    }
+
+    pub fn list(&self) -> Result<BTreeMap<String, Record>> {
+        let path = self.root.join("index.json");
+        if !path.try_exists()? {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_str(&read_to_string(path)?)?)
+    }
+
+    /// Forgets references fetched more than `max_age` ago and removes contents nothing refers to
+    pub fn prune(&self, max_age: Duration) -> Result<Vec<String>> {
+        let mut index = self.list()?;
+        let threshold = now().saturating_sub(max_age.as_secs());
+        let removed = index
+            .iter()
+            .filter(|(_, record)| record.fetched < threshold)
+            .map(|(reference, _)| reference.clone())
+            .collect::<Vec<_>>();
+        for reference in &removed {
+            index.remove(reference);
+        }
+        self.save(&index)?;
+
+        let blobs = self.blobs();
+        if blobs.try_exists()? {
+            let used = index
+                .values()
+                .map(|record| record.digest.as_str())
+                .collect::<BTreeSet<_>>();
+            for entry in read_dir(&blobs)? {
+                let entry = entry?;
+                if !used.contains(entry.file_name().to_string_lossy().as_ref()) {
+                    remove_dir_all(entry.path())?;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        if self.root.try_exists()? {
+            remove_dir_all(&self.root)?;
+        }
+        Ok(())
+    }
+
+    fn insert(
+        &self,
+        fetch: impl FnOnce(&Path) -> Result<(PathBuf, String)>,
+        key: impl FnOnce(&Record) -> String,
+    ) -> Result<(PathBuf, Record)> {
+        let (path, resolved) = fetch(&self.blobs())?;
+        let record = Record {
+            digest: path.strip_prefix(self.blobs())?.to_string_lossy().into_owned(),
+            fetched: now(),
+            resolved,
+        };
+        // NOTE: re-read the index, fetching may take a while and builds may run concurrently
+        let mut index = self.list()?;
+        index.insert(key(&record), record.clone());
+        self.save(&index)?;
+        Ok((path, record))
+    }
+
+    fn save(&self, index: &BTreeMap<String, Record>) -> Result<()> {
+        // NOTE: builds may run concurrently, so replace the index atomically
+        create_dir_all(&self.root)?;
+        let tmp = self.root.join(format!(".index.{}.tmp", std::process::id()));
+        write(&tmp, serde_json::to_string_pretty(index)?)?;
+        rename(&tmp, self.root.join("index.json"))?;
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(blobs: &Path, digest: &str) -> Result<(PathBuf, String)> {
+        let path = blobs.join(digest);
+        create_dir_all(&path)?;
+        Ok((path, format!("foo@{digest}")))
+    }
+
+    #[test]
+    fn fetch() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::at(root.path());
+        let (path, record) = cache.fetch("foo", |blobs| put(blobs, "sha256-1")).unwrap();
+        assert_eq!(path, cache.blobs().join("sha256-1"));
+        assert_eq!(record.resolved, "foo@sha256-1");
+        assert_eq!(
+            cache
+                .fetch("foo", |_| panic!("cached reference is fetched again"))
+                .unwrap()
+                .0,
+            path
+        );
+        assert_eq!(cache.list().unwrap()["foo"].digest, "sha256-1");
+
+        remove_dir_all(&path).unwrap();
+        assert_eq!(
+            cache.fetch("foo", |blobs| put(blobs, "sha256-2")).unwrap().0,
+            cache.blobs().join("sha256-2")
+        );
+    }
+
+    #[test]
+    fn refresh() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::at(root.path());
+        let (_, record) = cache
+            .refresh(|blobs| put(blobs, "sha256-1"), |record| record.resolved.clone())
+            .unwrap();
+        assert_eq!(record.integrity(), "sha256:1");
+
+        // NOTE: the mutable reference moved, the immutable one it used to resolve to is still served
+        let (path, record) = cache
+            .refresh(|blobs| put(blobs, "sha256-2"), |record| record.resolved.clone())
+            .unwrap();
+        assert_eq!(
+            (path, record.resolved.as_str()),
+            (cache.blobs().join("sha256-2"), "foo@sha256-2")
+        );
+        assert_eq!(
+            cache.list().unwrap().into_keys().collect::<Vec<_>>(),
+            ["foo@sha256-1", "foo@sha256-2"]
+        );
+        assert_eq!(
+            cache
+                .fetch("foo@sha256-1", |_| panic!("cached reference is fetched again"))
+                .unwrap()
+                .0,
+            cache.blobs().join("sha256-1")
+        );
+    }
+
+    #[test]
+    fn prune() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::at(root.path());
+        cache.fetch("new", |blobs| put(blobs, "sha256-new")).unwrap();
+        cache.fetch("old", |blobs| put(blobs, "sha256-old")).unwrap();
+        put(&cache.blobs(), ".sha256-partial.tmp").unwrap();
+
+        let mut index = cache.list().unwrap();
+        index.get_mut("old").unwrap().fetched = 0;
+        cache.save(&index).unwrap();
+
+        assert_eq!(cache.prune(Duration::from_secs(60)).unwrap(), ["old"]);
+        assert_eq!(cache.list().unwrap().into_keys().collect::<Vec<_>>(), ["new"]);
+        assert!(cache.blobs().join("sha256-new").exists());
+        assert!(!cache.blobs().join("sha256-old").exists());
+        assert!(!cache.blobs().join(".sha256-partial.tmp").exists());
+
+        cache.clear().unwrap();
+        assert!(!root.path().exists());
+        assert!(cache.list().unwrap().is_empty());
+    }
+}
@@ -2,18 +2,32 @@ use std::path::PathBuf;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[cfg(feature = "artifact")]
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[cfg(feature = "artifact")]
     #[error(transparent)]
     Oci(#[from] oci_spec::distribution::ParseError),
+    #[cfg(feature = "package")]
+    #[error(transparent)]
+    OciSpec(#[from] oci_spec::OciSpecError),
     #[error(transparent)]
     Path(#[from] std::path::StripPrefixError),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
-    #[cfg(feature = "tarball")]
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    #[error(transparent)]
+    Ureq(#[from] ureq::Error),
+    #[cfg(any(feature = "git", feature = "tarball"))]
     #[error(transparent)]
     Url(#[from] url::ParseError),
+    #[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+    #[error("archive entry escapes the destination directory: {path:?}")]
+    ArchivePathIllegal { path: PathBuf },
+    #[error("cache directory is not found, set $DEVPP_CACHE_DIR, $XDG_CACHE_HOME or $HOME")]
+    CacheNotFound,
     #[error("config is not specified, found within search path {entries:?}")]
     ConfigAmbiguous { entries: Vec<PathBuf> },
     #[error(
@@ -25,16 +39,69 @@ pub enum Error {
     ConfigPermissionDenied { config: PathBuf, entries: Vec<PathBuf> },
     #[error("the project must have a .devcontainer/ folder at the root of the project workspace folder")]
     DotdevNotFound,
+    #[cfg(feature = "git")]
+    #[error("`{command}` failed with {status}: {stderr}")]
+    GitCommand {
+        command: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    #[cfg(feature = "git")]
+    #[error("git feature reference must look like git+<url>#rev=<rev>&path=<path>: {id:?}")]
+    GitReferenceInvalid { id: String },
     #[error("the local feature's sub-folder must contain a install.sh entrypoint script: {id:?}")]
     FeatureEntrypointNotFound { id: String },
     #[error("the local feature's sub-folder must contain a devcontainer-feature.json file: {id:?}")]
     FeatureMetadataNotFound { id: String },
+    #[error("options {options:?} of feature {feature:?} are both passed to install.sh as {env:?}")]
+    FeatureOptionCollision {
+        env: String,
+        feature: String,
+        options: Vec<String>,
+    },
+    #[error("option {option:?} of feature {feature:?} must be one of {allowed:?}, but got {value:?}")]
+    FeatureOptionInvalid {
+        allowed: Vec<String>,
+        feature: String,
+        option: String,
+        value: String,
+    },
+    #[error("feature {feature:?} has no option {option:?}, expected one of {known:?}")]
+    FeatureOptionUnknown {
+        feature: String,
+        known: Vec<String>,
+        option: String,
+    },
     #[error("the sub-folder name must match the feature's id field: {id:?} expected {expected:?}, but got {got:?}")]
     FeatureIdMismatch {
         expected: String,
         got: std::ffi::OsString,
         id: String,
     },
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    #[error("server responded with {status} for {url:?}")]
+    HttpStatus { status: u16, url: String },
+    #[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+    #[error("feature {id:?} does not match the lockfile: expected {expected:?}, but got {got:?}")]
+    LockIntegrityMismatch { expected: String, got: String, id: String },
+    #[cfg(feature = "artifact")]
+    #[error("registry {registry:?} requested an unsupported or unsatisfiable authentication")]
+    OciAuthInvalid { registry: String },
+    #[cfg(feature = "artifact")]
+    #[error("content digest mismatch: expected {expected:?}, but got {got:?}")]
+    OciDigestMismatch { expected: String, got: String },
+    #[cfg(feature = "artifact")]
+    #[error("the artifact does not contain a {media_type:?} layer: {reference:?}", media_type = crate::feat::LAYER_MEDIA_TYPE)]
+    OciLayerNotFound { reference: String },
+    #[cfg(feature = "artifact")]
+    #[error("the image is not published for {platform:?}: {reference:?}")]
+    OciPlatformNotFound { platform: String, reference: String },
+    #[cfg(feature = "artifact")]
+    #[error("no published version matches {reference:?}")]
+    OciTagNotFound { reference: String },
+    #[cfg(feature = "package")]
+    #[error("no devcontainer-feature.json is found in {path:?} or its sub-folders")]
+    PackageNotFound { path: PathBuf },
     #[cfg(feature = "tarball")]
     #[error("the .tgz archive file must be named devcontainer-feature-<featureId>.tgz: {id:?}")]
     ReferenceInvalidArgument { id: String },
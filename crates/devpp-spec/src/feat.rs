@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
 use std::path::Path;
 use std::path::PathBuf;
@@ -9,10 +10,29 @@ use serde::Serialize;
 #[cfg(feature = "tarball")]
 use url::Url;
 
+#[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+use crate::cache::Cache;
+#[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+use crate::cache::Record;
 use crate::devc::Config;
 use crate::error::Error;
 use crate::error::Result;
 use crate::feat::generated::Feature as GeneratedFeature;
+use crate::feat::generated::FeatureOption;
+#[cfg(feature = "git")]
+use crate::git;
+#[cfg(feature = "git")]
+use crate::git::GitReference;
+use crate::lock::Locked;
+#[cfg(feature = "artifact")]
+use crate::oci::DockerConfig;
+#[cfg(feature = "artifact")]
+use crate::oci::Registry;
+#[cfg(feature = "tarball")]
+use crate::tarball;
+
+/// @see: https://containers.dev/implementors/features-distribution/#oci-registry
+pub const LAYER_MEDIA_TYPE: &str = "application/vnd.devcontainers.layer.v1+tar";
 
 #[allow(clippy::all)]
 pub mod generated {
@@ -25,6 +45,9 @@ pub struct Feature {
     pub entrypoint: PathBuf,
     #[serde(flatten)]
     pub inner: GeneratedFeature,
+    /// Resolution of a remote feature, for the lockfile
+    #[serde(skip)]
+    pub locked: Option<Locked>,
     #[serde(skip)]
     pub merger: Option<PathBuf>,
     #[serde(skip)]
@@ -32,43 +55,180 @@ pub struct Feature {
 }
 
 impl Feature {
-    pub fn new(reference: &Reference) -> Result<Self> {
+    /// Fetches the feature, remote features are pinned to `pin` when it is given
+    #[cfg_attr(
+        not(any(feature = "artifact", feature = "git", feature = "tarball")),
+        allow(unused_variables)
+    )]
+    pub fn new(reference: &Reference, pin: Option<&Locked>) -> Result<Self> {
         let this = match &reference.kind {
             #[cfg(feature = "artifact")]
-            ReferenceKind::Artifact { .. } => unimplemented!(),
-            // @see: https://containers.dev/implementors/features/#folder-structure
-            ReferenceKind::Local { path } => {
-                let path_metadata = path.join("devcontainer-feature.json");
-                if !path_metadata.try_exists()? {
-                    return Err(Error::FeatureMetadataNotFound {
-                        id: reference.id.clone(),
-                    });
+            ReferenceKind::Artifact { reference: oci } => {
+                let cache = Cache::new()?;
+                let fetch = |blobs: &Path| {
+                    let mut registry = Registry::new(DockerConfig::load()?);
+                    let target = match pin {
+                        Some(pin) => pin.resolved.parse()?,
+                        None => registry.resolve(oci)?,
+                    };
+                    registry.pull(&target, blobs)
                 };
-
-                let path_entrypoint = path.join("install.sh");
-                if !path_entrypoint.try_exists()? {
-                    return Err(Error::FeatureEntrypointNotFound {
-                        id: reference.id.clone(),
-                    });
-                }
-
-                let path_merger = path.join("configure.sh");
-
-                let mut s = read_to_string(&path_metadata)?;
-                json_strip_comments::strip(&mut s)?;
-                Self {
-                    merger: path_merger.try_exists()?.then_some(path_merger),
-                    entrypoint: path_entrypoint,
-                    inner: serde_json::from_str(&s)?,
-                    metadata: path_metadata,
-                }
+                // NOTE: tags may move, only digests are served from the cache without asking the registry
+                let key = pin.map(|pin| pin.resolved.clone());
+                let (path, record) = match key.or_else(|| oci.digest().map(|_| oci.whole())) {
+                    Some(key) => cache.fetch(&key, fetch)?,
+                    None => cache.refresh(fetch, |record| record.resolved.clone())?,
+                };
+                Self::from_dir(&path, reference)?.lock(&record, pin, reference)?
             }
+            #[cfg(feature = "git")]
+            ReferenceKind::Git { reference: repository } => {
+                let cache = Cache::new()?;
+                let fetch = |blobs: &Path| {
+                    let target = match pin {
+                        Some(pin) => GitReference::parse(&pin.resolved)?,
+                        None => repository.clone(),
+                    };
+                    git::fetch(&target, &cache.git(), blobs)
+                };
+                let (path, record) = match pin {
+                    Some(pin) => cache.fetch(&pin.resolved, fetch)?,
+                    None => cache.refresh(fetch, |record| record.resolved.clone())?,
+                };
+                Self::from_dir(&path.join(&repository.path), reference)?.lock(&record, pin, reference)?
+            }
+            ReferenceKind::Local { path } => Self::from_dir(path, reference)?,
             #[cfg(feature = "tarball")]
-            ReferenceKind::Tarball { .. } => unimplemented!(),
+            ReferenceKind::Tarball { url } => {
+                // NOTE: the URL itself is mutable, so the contents are indexed under their digest as well
+                let cache = Cache::new()?;
+                let fetch = |blobs: &Path| tarball::fetch(url, blobs);
+                let (path, record) = match pin {
+                    Some(pin) => cache.fetch(&format!("{url}@{}", pin.integrity), fetch)?,
+                    None => cache.refresh(fetch, |record| format!("{url}@{}", record.integrity()))?,
+                };
+                Self::from_dir(&path, reference)?.lock(&record, pin, reference)?
+            }
         };
         reference.validate(&this.inner.id)?;
         Ok(this)
     }
+
+    /// @see: https://containers.dev/implementors/features/#folder-structure
+    pub fn from_dir(path: &Path, reference: &Reference) -> Result<Self> {
+        let path_metadata = path.join("devcontainer-feature.json");
+        if !path_metadata.try_exists()? {
+            return Err(Error::FeatureMetadataNotFound {
+                id: reference.id.clone(),
+            });
+        };
+
+        let path_entrypoint = path.join("install.sh");
+        if !path_entrypoint.try_exists()? {
+            return Err(Error::FeatureEntrypointNotFound {
+                id: reference.id.clone(),
+            });
+        }
+
+        let path_merger = path.join("configure.sh");
+
+        let mut s = read_to_string(&path_metadata)?;
+        json_strip_comments::strip(&mut s)?;
+        Ok(Self {
+            merger: path_merger.try_exists()?.then_some(path_merger),
+            entrypoint: path_entrypoint,
+            inner: serde_json::from_str(&s)?,
+            locked: None,
+            metadata: path_metadata,
+        })
+    }
+
+    /// User options checked against the declared ones, with the defaults filled in
+    ///
+    /// @see: https://containers.dev/implementors/features/#options-property
+    pub fn options(&self, opts: &BTreeMap<String, String>) -> Result<BTreeMap<String, String>> {
+        if let Some(key) = opts.keys().find(|key| !self.inner.options.contains_key(*key)) {
+            return Err(Error::FeatureOptionUnknown {
+                feature: self.inner.id.clone(),
+                known: self.inner.options.keys().cloned().collect(),
+                option: key.clone(),
+            });
+        }
+
+        let mut envs = BTreeMap::<String, &String>::new();
+        for key in self.inner.options.keys() {
+            if let Some(other) = envs.insert(env_name(key), key) {
+                return Err(Error::FeatureOptionCollision {
+                    env: env_name(key),
+                    feature: self.inner.id.clone(),
+                    options: vec![other.clone(), key.clone()],
+                });
+            }
+        }
+
+        let mut options = BTreeMap::new();
+        for (key, option) in &self.inner.options {
+            let (type_, default, allowed) = match option {
+                FeatureOption::Variant0 { default, type_, .. } => (type_, default.to_string(), None),
+                FeatureOption::Variant1 {
+                    default, enum_, type_, ..
+                } => (type_, default.clone(), Some(enum_.clone())),
+                // NOTE: proposals are merely suggestions, the install script handles arbitrary values
+                FeatureOption::Variant2 { default, type_, .. } => (type_, default.clone(), None),
+            };
+            let allowed = match type_.as_str() {
+                "boolean" => Some(vec![String::from("true"), String::from("false")]),
+                _ => allowed,
+            };
+            let value = opts.get(key).cloned().unwrap_or(default);
+            if let Some(allowed) = allowed
+                && !allowed.contains(&value)
+            {
+                return Err(Error::FeatureOptionInvalid {
+                    allowed,
+                    feature: self.inner.id.clone(),
+                    option: key.clone(),
+                    value,
+                });
+            }
+            options.insert(key.clone(), value);
+        }
+        Ok(options)
+    }
+
+    #[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+    fn lock(mut self, record: &Record, pin: Option<&Locked>, reference: &Reference) -> Result<Self> {
+        let integrity = record.integrity();
+        if let Some(pin) = pin
+            && pin.integrity != integrity
+        {
+            return Err(Error::LockIntegrityMismatch {
+                expected: pin.integrity.clone(),
+                got: integrity,
+                id: reference.id.clone(),
+            });
+        }
+        self.locked = Some(Locked {
+            depends_on: self.inner.depends_on.keys().cloned().collect(),
+            integrity,
+            resolved: record.resolved.clone(),
+            version: self.inner.version.clone(),
+        });
+        Ok(self)
+    }
+}
+
+/// Environment variable `install.sh` receives the option as, e.g. `1.version-tag` becomes `_VERSION_TAG`
+///
+/// @see: https://containers.dev/implementors/features/#option-resolution
+pub fn env_name(option: &str) -> String {
+    let name = option
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let rest = name.trim_start_matches(|c: char| c.is_ascii_digit() || c == '_');
+    let prefix = if rest.len() < name.len() { "_" } else { "" };
+    format!("{prefix}{rest}").to_ascii_uppercase()
 }
 
 #[derive(Clone, Debug)]
@@ -87,8 +247,29 @@ impl Reference {
 
     pub fn validate(&self, id: &str) -> Result<()> {
         match &self.kind {
+            // @see: https://containers.dev/implementors/features-distribution/#oci-registry
             #[cfg(feature = "artifact")]
-            ReferenceKind::Artifact { .. } => unimplemented!(),
+            ReferenceKind::Artifact { reference } => {
+                let got = reference.repository().rsplit('/').next().unwrap_or_default();
+                if got != id {
+                    return Err(Error::FeatureIdMismatch {
+                        expected: id.to_string(),
+                        got: got.into(),
+                        id: self.id.clone(),
+                    });
+                }
+            }
+            #[cfg(feature = "git")]
+            ReferenceKind::Git { reference } => {
+                let got = reference.name();
+                if got != id {
+                    return Err(Error::FeatureIdMismatch {
+                        expected: id.to_string(),
+                        got: got.into(),
+                        id: self.id.clone(),
+                    });
+                }
+            }
             ReferenceKind::Local { path } => {
                 let got = path.iter().next_back().unwrap();
                 if got.to_str().unwrap() != id {
@@ -99,8 +280,18 @@ impl Reference {
                     });
                 }
             }
+            // @see: https://containers.dev/implementors/features-distribution/#directly-reference-tarball
             #[cfg(feature = "tarball")]
-            ReferenceKind::Tarball { .. } => unimplemented!(),
+            ReferenceKind::Tarball { url } => {
+                let got = tarball::feature_id(url).unwrap_or_default();
+                if got != id {
+                    return Err(Error::FeatureIdMismatch {
+                        expected: id.to_string(),
+                        got: got.into(),
+                        id: self.id.clone(),
+                    });
+                }
+            }
         };
         Ok(())
     }
@@ -112,6 +303,9 @@ pub enum ReferenceKind {
     /// @see: https://containers.dev/implementors/features-distribution/#oci-registry
     #[cfg(feature = "artifact")]
     Artifact { reference: OciReference },
+    /// Feature folder within a git repository, e.g. `git+file:///srv/features.git#rev=v2&path=src/rust`
+    #[cfg(feature = "git")]
+    Git { reference: GitReference },
     /// @see: https://containers.dev/implementors/features-distribution/#addendum-locally-referenced
     Local { path: PathBuf },
     /// @see: https://containers.dev/implementors/features-distribution/#directly-reference-tarball
@@ -137,6 +331,13 @@ impl ReferenceKind {
             return Ok(Self::Local { path });
         }
 
+        #[cfg(feature = "git")]
+        if id.starts_with("git+") {
+            return Ok(Self::Git {
+                reference: GitReference::parse(id)?,
+            });
+        }
+
         #[cfg(feature = "artifact")]
         if let Ok(reference) = id.parse() {
             return Ok(Self::Artifact { reference });
@@ -148,11 +349,7 @@ impl ReferenceKind {
                 return Err(Error::ReferenceSchemeMismatch { id: id.to_string() });
             }
 
-            let path = Path::new(url.path());
-            let file_name = path.file_name().unwrap().to_str().unwrap();
-
-            // TODO: check featureId
-            if !(file_name.starts_with("devcontainer-feature-") && file_name.ends_with(".tgz")) {
+            if tarball::feature_id(&url).is_none() {
                 return Err(Error::ReferenceInvalidArgument { id: id.to_string() });
             }
 
@@ -172,9 +369,10 @@ mod tests {
     fn run_feature(workspace: &Path) -> Result<()> {
         let config = Config::find_config(workspace, None)?;
         let devc = DevContainer::new(std::fs::read_to_string(&config.path)?)?;
-        for id in devc.common.features.keys() {
+        for (id, options) in &devc.common.features {
             let reference = Reference::new(id, &config)?;
-            Feature::new(&reference)?;
+            let options = options.iter().map(|(key, value)| (key.clone(), value.to_string()));
+            Feature::new(&reference, None)?.options(&options.collect())?;
         }
         Ok(())
     }
@@ -206,6 +404,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn env_name() {
+        assert_eq!(super::env_name("version"), "VERSION");
+        assert_eq!(super::env_name("installTools"), "INSTALLTOOLS");
+        assert_eq!(super::env_name("1.version-tag"), "_VERSION_TAG");
+        assert_eq!(super::env_name("_private"), "_PRIVATE");
+    }
+
+    #[test]
+    fn feature_option_collision() {
+        let workspace = root("tests/fixtures/feature_option_collision");
+        match run_feature(&workspace) {
+            Err(Error::FeatureOptionCollision { env, .. }) => assert_eq!(env, "INSTALL_TOOLS"),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn feature_option_invalid() {
+        let workspace = root("tests/fixtures/feature_option_invalid");
+        match run_feature(&workspace) {
+            Err(Error::FeatureOptionInvalid { allowed, option, .. }) => {
+                assert_eq!(allowed, ["stable", "nightly"]);
+                assert_eq!(option, "channel");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn feature_option_unknown() {
+        let workspace = root("tests/fixtures/feature_option_unknown");
+        match run_feature(&workspace) {
+            Err(Error::FeatureOptionUnknown { option, .. }) => assert_eq!(option, "channnel"),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn feature_options() {
+        let path = root("tests/fixtures/feature_option_invalid/.devcontainer/features/foo");
+        let reference = Reference {
+            id: String::from("./features/foo"),
+            kind: ReferenceKind::Local { path: path.clone() },
+        };
+        let feature = Feature::from_dir(&path, &reference).unwrap();
+        let opts = BTreeMap::from([(String::from("version"), String::from("2.0"))]);
+        assert_eq!(
+            feature.options(&opts).unwrap(),
+            BTreeMap::from([
+                (String::from("channel"), String::from("stable")),
+                (String::from("install"), String::from("true")),
+                (String::from("version"), String::from("2.0")),
+            ])
+        );
+        let opts = BTreeMap::from([(String::from("install"), String::from("yes"))]);
+        match feature.options(&opts) {
+            Err(Error::FeatureOptionInvalid { allowed, .. }) => assert_eq!(allowed, ["true", "false"]),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[cfg(feature = "tarball")]
+    #[test]
+    fn lock_integrity_mismatch() {
+        let url = Url::parse("https://example.com/devcontainer-feature-baz.tgz").unwrap();
+        let reference = Reference {
+            id: url.to_string(),
+            kind: ReferenceKind::Tarball { url: url.clone() },
+        };
+        let path = root("tests/fixtures/feature_id_mismatch/.devcontainer/features/bar");
+        let record = Record {
+            digest: String::from("sha256-1"),
+            fetched: 0,
+            resolved: url.to_string(),
+        };
+
+        let feature = Feature::from_dir(&path, &reference)
+            .unwrap()
+            .lock(&record, None, &reference)
+            .unwrap();
+        let locked = feature.locked.clone().unwrap();
+        assert_eq!(locked.integrity, "sha256:1");
+        assert_eq!(locked.version, "0.0.0");
+
+        let pin = Locked {
+            integrity: String::from("sha256:2"),
+            ..locked
+        };
+        match feature.lock(&record, Some(&pin), &reference) {
+            Err(Error::LockIntegrityMismatch { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
     fn run_reference(workspace: &Path) -> Result<()> {
         let config = Config::find_config(workspace, None)?;
         let devc = DevContainer::new(std::fs::read_to_string(&config.path)?)?;
@@ -270,4 +563,40 @@ mod tests {
             other => panic!("{other:?}"),
         }
     }
+
+    #[cfg(feature = "tarball")]
+    #[test]
+    fn tarball_id_mismatch() {
+        use std::collections::HashMap;
+
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(vec![], flate2::Compression::default()));
+        for (path, data) in [
+            ("devcontainer-feature.json", r#"{"id":"bar","version":"1.0.0"}"#),
+            ("install.sh", "#!/bin/sh\n"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+        let data = builder.into_inner().unwrap().finish().unwrap();
+        let host = crate::tests::serve(
+            HashMap::from([("/devcontainer-feature-foo.tgz".to_string(), data)]),
+            None,
+        );
+
+        let url = Url::parse(&format!("http://{host}/devcontainer-feature-foo.tgz")).unwrap();
+        let reference = Reference {
+            id: url.to_string(),
+            kind: ReferenceKind::Tarball { url: url.clone() },
+        };
+        let cache = tempfile::tempdir().unwrap();
+        let (path, _) = tarball::fetch(&url, cache.path()).unwrap();
+        let feature = Feature::from_dir(&path, &reference).unwrap();
+        match reference.validate(&feature.inner.id) {
+            Err(Error::FeatureIdMismatch { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
 }
@@ -0,0 +1,204 @@
+use std::fs::create_dir_all;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use sha2::Digest;
+use sha2::Sha256;
+use url::Url;
+
+use crate::archive;
+use crate::error::Error;
+use crate::error::Result;
+
+/// Feature in a git repository: `git+<url>#rev=<rev>&path=<path>`
+#[derive(Clone, Debug)]
+pub struct GitReference {
+    /// Feature folder relative to the repository root
+    pub path: PathBuf,
+    /// Commit, tag or branch, `HEAD` by default
+    pub rev: String,
+    pub url: Url,
+}
+
+impl GitReference {
+    pub fn parse(id: &str) -> Result<Self> {
+        let invalid = || Error::GitReferenceInvalid { id: id.to_string() };
+        let mut url = Url::parse(id.strip_prefix("git+").ok_or_else(invalid)?)?;
+        let (mut path, mut rev) = (PathBuf::new(), String::from("HEAD"));
+        for (key, value) in url::form_urlencoded::parse(url.fragment().unwrap_or_default().as_bytes()) {
+            match key.as_ref() {
+                "path" => path = PathBuf::from(value.as_ref()),
+                "rev" if !value.is_empty() => rev = value.into_owned(),
+                _ => return Err(invalid()),
+            }
+        }
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(invalid());
+        }
+        url.set_fragment(None);
+        Ok(Self { path, rev, url })
+    }
+
+    /// Feature id the reference is expected to point at
+    pub fn name(&self) -> &str {
+        match self.path.file_name() {
+            Some(name) => name.to_str().unwrap_or_default(),
+            None => {
+                let name = self.url.path_segments().and_then(|mut segments| segments.next_back());
+                let name = name.unwrap_or_default();
+                name.strip_suffix(".git").unwrap_or(name)
+            }
+        }
+    }
+
+    /// Same reference with `rev` replaced
+    pub fn with_rev(&self, rev: &str) -> String {
+        let mut fragment = url::form_urlencoded::Serializer::new(String::new());
+        fragment.append_pair("rev", rev);
+        if !self.path.as_os_str().is_empty() {
+            fragment.append_pair("path", &self.path.to_string_lossy());
+        }
+        format!("git+{}#{}", self.url, fragment.finish())
+    }
+}
+
+/// Checks the repository out into `blobs` and returns the tree root, along with the commit-pinned reference
+///
+/// The repository is mirrored into `mirrors`, so that later revisions are fetched incrementally.
+pub fn fetch(reference: &GitReference, mirrors: &Path, blobs: &Path) -> Result<(PathBuf, String)> {
+    let mirror = mirrors.join(format!("{:x}", Sha256::digest(reference.url.as_str())));
+    if !mirror.try_exists()? {
+        create_dir_all(mirrors)?;
+        git(Command::new("git")
+            .args(["clone", "--mirror", "--quiet", reference.url.as_str()])
+            .arg(&mirror))?;
+    }
+
+    // NOTE: commits that are already mirrored are immutable, so stay offline for them
+    let commit = match rev_parse(&mirror, &reference.rev) {
+        Ok(commit) if commit.starts_with(&reference.rev) => commit,
+        _ => {
+            git(Command::new("git")
+                .arg("-C")
+                .arg(&mirror)
+                .args(["fetch", "--prune", "--quiet", "origin"]))?;
+            rev_parse(&mirror, &reference.rev)?
+        }
+    };
+
+    let name = format!("git-{commit}");
+    let dir = blobs.join(&name);
+    if !dir.try_exists()? {
+        let tree = git(Command::new("git")
+            .arg("-C")
+            .arg(&mirror)
+            .args(["archive", "--format=tar", &commit]))?;
+        archive::unpack(tree.as_slice(), blobs, &name)?;
+    }
+    Ok((dir, reference.with_rev(&commit)))
+}
+
+fn rev_parse(mirror: &Path, rev: &str) -> Result<String> {
+    let stdout = git(Command::new("git").arg("-C").arg(mirror).args([
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        "--end-of-options",
+        &format!("{rev}^{{commit}}"),
+    ]))?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+fn git(command: &mut Command) -> Result<Vec<u8>> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let args = command.get_args().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>();
+        return Err(Error::GitCommand {
+            command: format!("git {}", args.join(" ")),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+
+    /// Bare repository with a feature under `src/foo`, tagged `v1` and `v2`
+    fn repository(root: &Path) -> PathBuf {
+        let (work, bare) = (root.join("work"), root.join("features.git"));
+        create_dir_all(work.join("src/foo")).unwrap();
+        let run = |args: &[&str]| {
+            git(Command::new("git")
+                .arg("-C")
+                .arg(&work)
+                .args(["-c", "user.name=devpp", "-c", "user.email=devpp@localhost"])
+                .args(args))
+            .unwrap();
+        };
+        run(&["init", "--quiet"]);
+        for version in ["1", "2"] {
+            write(
+                work.join("src/foo/devcontainer-feature.json"),
+                format!(r#"{{"id":"foo","version":"{version}.0.0"}}"#),
+            )
+            .unwrap();
+            write(work.join("src/foo/install.sh"), "#!/bin/sh\n").unwrap();
+            run(&["add", "."]);
+            run(&["commit", "--quiet", "-m", version]);
+            run(&["tag", &format!("v{version}")]);
+        }
+        git(Command::new("git")
+            .args(["clone", "--bare", "--quiet"])
+            .arg(&work)
+            .arg(&bare))
+        .unwrap();
+        bare
+    }
+
+    #[test]
+    fn fetch() {
+        let root = tempfile::tempdir().unwrap();
+        let bare = repository(root.path());
+        let (mirrors, blobs) = (root.path().join("git"), root.path().join("blobs"));
+
+        let id = format!("git+file://{}#rev=v1&path=src/foo", bare.display());
+        let reference = GitReference::parse(&id).unwrap();
+        assert_eq!(reference.name(), "foo");
+        let (dir, resolved) = super::fetch(&reference, &mirrors, &blobs).unwrap();
+        let metadata = std::fs::read_to_string(dir.join("src/foo/devcontainer-feature.json")).unwrap();
+        assert!(metadata.contains(r#""version":"1.0.0""#), "{metadata}");
+
+        // NOTE: the pinned commit is served from the mirror, even when the origin is gone
+        std::fs::remove_dir_all(&bare).unwrap();
+        let pinned = GitReference::parse(&resolved).unwrap();
+        assert_eq!(pinned.path, Path::new("src/foo"));
+        assert_eq!(super::fetch(&pinned, &mirrors, &blobs).unwrap(), (dir, resolved));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for id in [
+            "file:///srv/features.git",
+            "git+file:///srv/features.git#path=../foo",
+            "git+file:///srv/features.git#path=/foo",
+            "git+file:///srv/features.git#branch=main",
+        ] {
+            match GitReference::parse(id) {
+                Err(Error::GitReferenceInvalid { .. }) => {}
+                other => panic!("{id}: {other:?}"),
+            }
+        }
+        let reference = GitReference::parse("git+file:///srv/features.git").unwrap();
+        assert_eq!((reference.rev.as_str(), reference.name()), ("HEAD", "features"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::error::Result;
+
+/// Label the image records the metadata of its features and config in
+///
+/// @see: https://containers.dev/implementors/spec/#image-metadata
+pub const METADATA_LABEL: &str = "devcontainer.metadata";
+
+/// Defaults a container of the image runs with, unset when the image leaves them empty
+///
+/// @see: https://github.com/opencontainers/image-spec/blob/main/config.md#properties
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct ImageConfig {
+    #[serde(default, rename = "Cmd")]
+    pub cmd: Option<Vec<String>>,
+    #[serde(default, rename = "Entrypoint")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(default, rename = "Labels")]
+    pub labels: Option<BTreeMap<String, String>>,
+    #[serde(default, rename = "User")]
+    pub user: Option<String>,
+}
+
+impl ImageConfig {
+    /// Entries of the metadata label, which may also hold a single entry
+    pub fn get_metadata(&self) -> Result<Vec<Map<String, Value>>> {
+        let Some(label) = self.labels.as_ref().and_then(|labels| labels.get(METADATA_LABEL)) else {
+            return Ok(vec![]);
+        };
+        Ok(match serde_json::from_str(label)? {
+            Value::Array(entries) => entries
+                .into_iter()
+                .filter_map(|entry| match entry {
+                    Value::Object(entry) => Some(entry),
+                    _ => None,
+                })
+                .collect(),
+            Value::Object(entry) => vec![entry],
+            _ => vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn get_metadata() {
+        let config = |label: &str| ImageConfig {
+            labels: Some(BTreeMap::from([(METADATA_LABEL.to_string(), label.to_string())])),
+            ..Default::default()
+        };
+        let metadata = config(r#"[{"id":"a"},{"remoteUser":"vscode"}]"#)
+            .get_metadata()
+            .unwrap();
+        assert_eq!(
+            Value::from(metadata),
+            json!([{ "id": "a" }, { "remoteUser": "vscode" }])
+        );
+        let metadata = config(r#"{"remoteUser":"vscode"}"#).get_metadata().unwrap();
+        assert_eq!(Value::from(metadata), json!([{ "remoteUser": "vscode" }]));
+        assert!(ImageConfig::default().get_metadata().unwrap().is_empty());
+    }
+}
@@ -1,33 +1,149 @@
+#[cfg(any(feature = "artifact", feature = "git", feature = "tarball"))]
+mod archive;
+pub mod cache;
 pub mod devc;
 pub mod devpp;
 pub mod error;
 pub mod feat;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod image;
+pub mod lock;
+pub mod merge;
+#[cfg(feature = "artifact")]
+pub mod oci;
+#[cfg(feature = "package")]
+pub mod package;
+#[cfg(feature = "tarball")]
+pub mod tarball;
 
-use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
 
 use crate::devc::DevContainer;
 use crate::error::Result;
 use crate::feat::Feature;
 
-pub fn get_metadata(devc: &DevContainer, features: &[&Feature]) -> Result<String> {
-    let mut metadata = features.iter().fold(vec![], |mut acc, feature| {
-        acc.push(json!({
-            "customizations": feature.inner.customizations,
-        }));
-        acc
-    });
-    metadata.push(json!({
-        "customizations": devc.common.customizations,
-    }));
-    Ok(serde_json::to_string(&metadata)?)
+/// Properties of features and configs that the image carries in its `devcontainer.metadata` label
+///
+/// @see: https://containers.dev/implementors/spec/#image-metadata
+const METADATA_KEYS: &[&str] = &[
+    "capAdd",
+    "containerEnv",
+    "containerUser",
+    "customizations",
+    "entrypoint",
+    "forwardPorts",
+    "hostRequirements",
+    "id",
+    "init",
+    "mounts",
+    "onCreateCommand",
+    "otherPortsAttributes",
+    "overrideCommand",
+    "portsAttributes",
+    "postAttachCommand",
+    "postCreateCommand",
+    "postStartCommand",
+    "privileged",
+    "remoteEnv",
+    "remoteUser",
+    "securityOpt",
+    "shutdownAction",
+    "updateContentCommand",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "waitFor",
+];
+
+/// Entries of the `devcontainer.metadata` label, `features` must be in install order, each along with
+/// the reference it is installed by
+pub fn get_metadata(devc: &DevContainer, features: &[(&str, &Feature)]) -> Result<Vec<Map<String, Value>>> {
+    let mut metadata = vec![];
+    for (reference, feature) in features {
+        let mut entry = pick_metadata(serde_json::to_value(&feature.inner)?);
+        // NOTE: the reference rather than the id, which unrelated features may share
+        entry.insert(String::from("id"), Value::from(*reference));
+        // NOTE: the exact version a floating tag was resolved to
+        entry.insert(String::from("version"), Value::from(feature.inner.version.clone()));
+        metadata.push(entry);
+    }
+    metadata.push(pick_metadata(serde_json::to_value(devc)?));
+    Ok(metadata)
+}
+
+fn pick_metadata(value: Value) -> Map<String, Value> {
+    let Value::Object(map) = value else {
+        return Map::new();
+    };
+    map.into_iter()
+        .filter(|(key, _)| METADATA_KEYS.contains(&key.as_str()))
+        .filter(|(_, value)| match value {
+            Value::Array(array) => !array.is_empty(),
+            Value::Null => false,
+            Value::Object(map) => !map.is_empty(),
+            _ => true,
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    use std::collections::HashMap;
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    use std::io::BufRead;
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    use std::io::BufReader;
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    use std::io::Write;
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    use std::net::TcpListener;
     use std::path::Path;
     use std::path::PathBuf;
 
     pub(crate) fn root(path: impl AsRef<Path>) -> PathBuf {
         Path::new(env!("CARGO_MANIFEST_DIR")).join(path)
     }
+
+    /// Minimal HTTP server stand-in, `auth` is a `WWW-Authenticate` challenge and the expected `Authorization` header
+    #[cfg(any(feature = "artifact", feature = "tarball"))]
+    pub(crate) fn serve(routes: HashMap<String, Vec<u8>>, auth: Option<(String, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let (mut path, mut authorization) = (String::new(), None);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if let Some(rest) = line.strip_prefix("GET ") {
+                        path = rest.split(' ').next().unwrap().to_string();
+                    }
+                    if let Some((key, value)) = line.split_once(':')
+                        && key.eq_ignore_ascii_case("authorization")
+                    {
+                        authorization = Some(value.trim().to_string());
+                    }
+                    line.clear();
+                }
+                let route = path.split('?').next().unwrap();
+                let (status, headers, body) = match (&auth, routes.get(route)) {
+                    (Some((challenge, expected)), _) if authorization.as_ref() != Some(expected) => {
+                        ("401 Unauthorized", format!("WWW-Authenticate: {challenge}\r\n"), vec![])
+                    }
+                    (_, Some(body)) => ("200 OK", String::new(), body.clone()),
+                    (_, None) => ("404 Not Found", String::new(), vec![]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        format!("localhost:{}", addr.port())
+    }
 }
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::devc::Config;
+use crate::error::Result;
+
+/// @see: https://github.com/devcontainers/spec/blob/main/proposals/devcontainer-lockfile.md
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub features: BTreeMap<String, Locked>,
+}
+
+/// Resolution of a remote feature reference
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Locked {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Digest of the feature contents, e.g. `sha256:<hex>`
+    pub integrity: String,
+    /// Immutable reference the feature was fetched from
+    pub resolved: String,
+    pub version: String,
+}
+
+impl Lockfile {
+    /// `.devcontainer-lock.json` next to `.devcontainer.json`, `devcontainer-lock.json` otherwise
+    pub fn path(config: &Config) -> PathBuf {
+        let name = match config.path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.starts_with('.') => ".devcontainer-lock.json",
+            _ => "devcontainer-lock.json",
+        };
+        config.path.with_file_name(name)
+    }
+
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        let path = Self::path(config);
+        if !path.try_exists()? {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&read_to_string(path)?)?))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let mut s = serde_json::to_string_pretty(self)?;
+        s.push('\n');
+        write(Self::path(config), s)?;
+        Ok(())
+    }
+
+    /// First feature whose resolution differs between the lockfiles
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Option<&'a String> {
+        self.features
+            .keys()
+            .chain(other.features.keys())
+            .find(|id| self.features.get(*id) != other.features.get(*id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::root;
+
+    #[test]
+    fn lockfile() {
+        let config = Config::find_config(&root("tests/fixtures/lockfile"), None).unwrap();
+        assert_eq!(
+            Lockfile::path(&config),
+            config.path.parent().unwrap().join("devcontainer-lock.json")
+        );
+
+        let lockfile = Lockfile::load(&config).unwrap().unwrap();
+        let locked = &lockfile.features["ghcr.io/devcontainers/features/node:1"];
+        assert_eq!(locked.version, "1.6.2");
+        assert_eq!(locked.depends_on, ["ghcr.io/devcontainers/features/common-utils"]);
+
+        let mut other = lockfile.clone();
+        assert_eq!(lockfile.diff(&other), None);
+        other.features.insert(
+            String::from("https://example.com/devcontainer-feature-foo.tgz"),
+            locked.clone(),
+        );
+        assert_eq!(
+            lockfile.diff(&other).map(String::as_str),
+            Some("https://example.com/devcontainer-feature-foo.tgz")
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use serde_json::Map;
+use serde_json::Value;
+
+/// Properties where any `true` wins
+const OR_KEYS: &[&str] = &["init", "privileged"];
+
+/// Properties whose values are united, without duplicates
+const UNION_KEYS: &[&str] = &["capAdd", "forwardPorts", "securityOpt"];
+
+/// Properties collected into a list named after their plural, in order
+const COLLECT_KEYS: &[(&str, &str)] = &[
+    ("entrypoint", "entrypoints"),
+    ("onCreateCommand", "onCreateCommands"),
+    ("postAttachCommand", "postAttachCommands"),
+    ("postCreateCommand", "postCreateCommands"),
+    ("postStartCommand", "postStartCommands"),
+    ("updateContentCommand", "updateContentCommands"),
+];
+
+/// Objects merged key by key, the last value of a key wins
+const MAP_KEYS: &[&str] = &["containerEnv", "portsAttributes", "remoteEnv"];
+
+/// Properties where the last value wins
+const LAST_KEYS: &[&str] = &[
+    "containerUser",
+    "otherPortsAttributes",
+    "overrideCommand",
+    "remoteUser",
+    "shutdownAction",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "waitFor",
+];
+
+/// Merges the entries of a `devcontainer.metadata` label, from the base image to the devcontainer.json,
+/// into the configuration they add up to
+///
+/// Collected properties are renamed the way `devcontainer read-configuration` names them, e.g.
+/// `postCreateCommand` into `postCreateCommands`, and `customizations` become lists per tool.
+///
+/// @see: https://containers.dev/implementors/spec/#merge-logic
+pub fn merge(entries: &[Map<String, Value>]) -> Map<String, Value> {
+    let mut merged = Map::new();
+    for key in OR_KEYS {
+        let values = values(entries, key).collect::<Vec<_>>();
+        if !values.is_empty() {
+            let any = values.iter().any(|value| value.as_bool() == Some(true));
+            merged.insert(key.to_string(), Value::Bool(any));
+        }
+    }
+    for key in UNION_KEYS {
+        let mut union = vec![];
+        for value in values(entries, key).flat_map(as_list) {
+            if !union.contains(value) {
+                union.push(value.clone());
+            }
+        }
+        insert_list(&mut merged, key, union);
+    }
+    for (key, plural) in COLLECT_KEYS {
+        insert_list(&mut merged, plural, values(entries, key).cloned().collect());
+    }
+    for key in MAP_KEYS {
+        let map = values(entries, key)
+            .filter_map(Value::as_object)
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Map<_, _>>();
+        if !map.is_empty() {
+            merged.insert(key.to_string(), Value::Object(map));
+        }
+    }
+    for key in LAST_KEYS {
+        if let Some(value) = values(entries, key).last() {
+            merged.insert(key.to_string(), value.clone());
+        }
+    }
+
+    let mut customizations = Map::new();
+    for (tool, value) in values(entries, "customizations").filter_map(Value::as_object).flatten() {
+        if let Value::Array(list) = customizations
+            .entry(tool.clone())
+            .or_insert_with(|| Value::Array(vec![]))
+        {
+            list.push(value.clone());
+        }
+    }
+    if !customizations.is_empty() {
+        merged.insert(String::from("customizations"), Value::Object(customizations));
+    }
+    insert_list(&mut merged, "mounts", merge_mounts(entries));
+    let requirements = merge_host_requirements(entries);
+    if !requirements.is_empty() {
+        merged.insert(String::from("hostRequirements"), Value::Object(requirements));
+    }
+    merged
+}
+
+/// Non-null values of `key`, in entry order
+fn values<'a>(entries: &'a [Map<String, Value>], key: &'a str) -> impl DoubleEndedIterator<Item = &'a Value> {
+    entries
+        .iter()
+        .filter_map(move |entry| entry.get(key))
+        .filter(|value| !value.is_null())
+}
+
+fn as_list(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(list) => list,
+        value => std::slice::from_ref(value),
+    }
+}
+
+fn insert_list(merged: &mut Map<String, Value>, key: &str, list: Vec<Value>) {
+    if !list.is_empty() {
+        merged.insert(key.to_string(), Value::Array(list));
+    }
+}
+
+/// Mounts in order, the last one mounted on a target wins
+fn merge_mounts(entries: &[Map<String, Value>]) -> Vec<Value> {
+    let mut targets = vec![];
+    let mut mounts = vec![];
+    for mount in values(entries, "mounts").flat_map(as_list).rev() {
+        let target = get_mount_target(mount);
+        if target.is_some() && targets.contains(&target) {
+            continue;
+        }
+        targets.push(target);
+        mounts.push(mount.clone());
+    }
+    mounts.reverse();
+    mounts
+}
+
+/// `target` of a mount object, or of a `--mount` string such as `source=x,target=/y,type=volume`
+fn get_mount_target(mount: &Value) -> Option<&str> {
+    match mount {
+        Value::Object(map) => map.get("target").and_then(Value::as_str),
+        Value::String(s) => s.split(',').find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key.trim(), "destination" | "dst" | "target").then_some(value.trim())
+        }),
+        _ => None,
+    }
+}
+
+/// The highest requirement wins, key by key
+fn merge_host_requirements(entries: &[Map<String, Value>]) -> Map<String, Value> {
+    let mut merged = Map::new();
+    for requirements in values(entries, "hostRequirements").filter_map(Value::as_object) {
+        for (key, value) in requirements {
+            let higher = match (key.as_str(), merged.get(key)) {
+                (_, None) => true,
+                ("cpus", Some(prev)) => value.as_f64() > prev.as_f64(),
+                ("memory" | "storage", Some(prev)) => parse_bytes(value) > parse_bytes(prev),
+                ("gpu", Some(prev)) => rank_gpu(value) > rank_gpu(prev),
+                (_, Some(_)) => true,
+            };
+            if higher {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Size such as `4gb`
+///
+/// @see: https://containers.dev/implementors/json_reference/#min-host-reqs
+fn parse_bytes(value: &Value) -> Option<u64> {
+    let s = value.as_str()?.to_ascii_lowercase();
+    let (digits, unit) = match s.len().checked_sub(2).map(|i| s.split_at(i)) {
+        Some((digits, "tb")) => (digits, 1 << 40),
+        Some((digits, "gb")) => (digits, 1 << 30),
+        Some((digits, "mb")) => (digits, 1 << 20),
+        Some((digits, "kb")) => (digits, 1 << 10),
+        _ => (s.as_str(), 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// `true` or a `{ cores, memory }` object requires a GPU, `"optional"` merely asks for one
+fn rank_gpu(value: &Value) -> u8 {
+    match value {
+        Value::Bool(true) | Value::Object(_) => 2,
+        Value::String(s) if s == "optional" => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn entries(value: Value) -> Vec<Map<String, Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn merge() {
+        let entries = entries(json!([
+            {
+                "capAdd": ["SYS_PTRACE"],
+                "containerEnv": { "A": "image", "B": "image" },
+                "customizations": { "vscode": { "extensions": ["a"] } },
+                "hostRequirements": { "cpus": 4, "gpu": "optional", "memory": "8gb" },
+                "init": false,
+                "mounts": ["source=cache,target=/cache,type=volume"],
+                "postCreateCommand": "image",
+                "remoteUser": "root",
+            },
+            {
+                "capAdd": ["SYS_PTRACE", "NET_ADMIN"],
+                "entrypoint": "/feature-init.sh",
+                "hostRequirements": { "cpus": 2, "memory": "16gb" },
+                "id": "feature",
+                "mounts": [{ "source": "other", "target": "/cache", "type": "volume" }],
+                "privileged": true,
+                "postCreateCommand": ["feature", "--setup"],
+            },
+            {
+                "containerEnv": { "B": "config" },
+                "customizations": { "vscode": { "extensions": ["b"] } },
+                "hostRequirements": { "gpu": true, "storage": "32gb" },
+                "init": true,
+                "mounts": [{ "source": "home", "target": "/home", "type": "volume" }],
+                "postCreateCommand": { "config": "config" },
+                "remoteUser": "vscode",
+            },
+        ]));
+        assert_eq!(
+            Value::Object(super::merge(&entries)),
+            json!({
+                "capAdd": ["SYS_PTRACE", "NET_ADMIN"],
+                "containerEnv": { "A": "image", "B": "config" },
+                "customizations": { "vscode": [{ "extensions": ["a"] }, { "extensions": ["b"] }] },
+                "entrypoints": ["/feature-init.sh"],
+                "hostRequirements": { "cpus": 4, "gpu": true, "memory": "16gb", "storage": "32gb" },
+                "init": true,
+                "mounts": [
+                    { "source": "other", "target": "/cache", "type": "volume" },
+                    { "source": "home", "target": "/home", "type": "volume" },
+                ],
+                "postCreateCommands": ["image", ["feature", "--setup"], { "config": "config" }],
+                "privileged": true,
+                "remoteUser": "vscode",
+            })
+        );
+    }
+
+    #[test]
+    fn parse_bytes() {
+        assert_eq!(super::parse_bytes(&json!("4GB")), Some(4 << 30));
+        assert_eq!(super::parse_bytes(&json!("512")), Some(512));
+        assert_eq!(super::parse_bytes(&json!("lots")), None);
+    }
+}
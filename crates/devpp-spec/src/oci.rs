@@ -0,0 +1,693 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use oci_spec::distribution::Reference as OciReference;
+use oci_spec::image::ImageManifest;
+use semver::Version;
+use semver::VersionReq;
+use serde::Deserialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::archive;
+use crate::error::Error;
+use crate::error::Result;
+use crate::feat::LAYER_MEDIA_TYPE;
+use crate::image::ImageConfig;
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Manifests and indexes of container images, as OCI and Docker registries serve them
+const IMAGE_MEDIA_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+/// Docker Hub stores its credentials under the legacy index address
+const DOCKER_HUB_AUTH_KEY: &str = "https://index.docker.io/v1/";
+
+/// @see: https://github.com/docker/cli/blob/master/man/docker-config-json.5.md
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DockerConfig {
+    #[serde(default)]
+    pub auths: BTreeMap<String, DockerAuth>,
+    #[serde(default, rename = "credHelpers")]
+    pub cred_helpers: BTreeMap<String, String>,
+    #[serde(default, rename = "credsStore")]
+    pub creds_store: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DockerAuth {
+    #[serde(default)]
+    pub auth: Option<String>,
+    #[serde(default, rename = "identitytoken")]
+    pub identity_token: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Credentials {
+    pub password: String,
+    pub username: String,
+}
+
+impl DockerConfig {
+    /// Reads `$DOCKER_CONFIG/config.json`, falling back to `~/.docker/config.json`
+    pub fn load() -> Result<Self> {
+        let dir = match (std::env::var_os("DOCKER_CONFIG"), std::env::var_os("HOME")) {
+            (Some(dir), _) => PathBuf::from(dir),
+            (None, Some(home)) => Path::new(&home).join(".docker"),
+            (None, None) => return Ok(Self::default()),
+        };
+        Self::from_path(&dir.join("config.json"))
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        if !path.try_exists()? {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&read_to_string(path)?)?)
+    }
+
+    pub fn credentials(&self, registry: &str) -> Result<Option<Credentials>> {
+        let key = match registry {
+            "docker.io" | "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB_AUTH_KEY,
+            registry => registry,
+        };
+
+        if let Some(helper) = self.cred_helpers.get(key).or(self.creds_store.as_ref()) {
+            return credential_helper(helper, key);
+        }
+
+        let Some(auth) = self
+            .auths
+            .iter()
+            .find_map(|(k, v)| (strip_scheme(k) == strip_scheme(key)).then_some(v))
+        else {
+            return Ok(None);
+        };
+        if let Some(token) = &auth.identity_token {
+            return Ok(Some(Credentials {
+                // NOTE: docker uses this magic username for identity tokens
+                password: token.clone(),
+                username: String::from("<token>"),
+            }));
+        }
+        if let (Some(username), Some(password)) = (&auth.username, &auth.password) {
+            return Ok(Some(Credentials {
+                password: password.clone(),
+                username: username.clone(),
+            }));
+        }
+        if let Some(auth) = &auth.auth {
+            let decoded = String::from_utf8_lossy(&BASE64.decode(auth)?).into_owned();
+            let (username, password) = decoded.split_once(':').ok_or_else(|| Error::OciAuthInvalid {
+                registry: key.to_string(),
+            })?;
+            return Ok(Some(Credentials {
+                password: password.to_string(),
+                username: username.to_string(),
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// @see: https://github.com/docker/docker-credential-helpers#development
+fn credential_helper(helper: &str, registry: &str) -> Result<Option<Credentials>> {
+    #[derive(Deserialize)]
+    struct Output {
+        #[serde(rename = "Secret")]
+        secret: String,
+        #[serde(rename = "Username")]
+        username: String,
+    }
+
+    let mut child = Command::new(format!("docker-credential-{helper}"))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(registry.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        // NOTE: helpers exit with an error when they have no credentials for the registry
+        return Ok(None);
+    }
+    let output = serde_json::from_slice::<Output>(&output.stdout)?;
+    Ok(Some(Credentials {
+        password: output.secret,
+        username: output.username,
+    }))
+}
+
+fn strip_scheme(s: &str) -> &str {
+    let s = s
+        .strip_prefix("https://")
+        .or_else(|| s.strip_prefix("http://"))
+        .unwrap_or(s);
+    s.split('/').next().unwrap_or(s)
+}
+
+/// @see: https://github.com/opencontainers/distribution-spec/blob/main/spec.md
+pub struct Registry {
+    agent: ureq::Agent,
+    config: DockerConfig,
+    token: Option<String>,
+}
+
+impl Registry {
+    pub fn new(config: DockerConfig) -> Self {
+        Self {
+            agent: ureq::Agent::config_builder().http_status_as_error(false).build().into(),
+            config,
+            token: None,
+        }
+    }
+
+    /// Downloads the feature layer into `cache` and returns the directory it was extracted to,
+    /// along with the digest-pinned reference it was resolved to
+    pub fn pull(&mut self, reference: &OciReference, cache: &Path) -> Result<(PathBuf, String)> {
+        let (manifest, manifest_digest) = self.manifest(reference)?;
+        let resolved = format!("{}/{}@{manifest_digest}", reference.registry(), reference.repository());
+        let layer = manifest
+            .layers()
+            .iter()
+            .find(|layer| layer.media_type().to_string() == LAYER_MEDIA_TYPE)
+            .ok_or_else(|| Error::OciLayerNotFound {
+                reference: reference.whole(),
+            })?;
+
+        let digest = layer.digest().to_string();
+        let dir = cache.join(digest.replace(':', "-"));
+        if dir.try_exists()? {
+            return Ok((dir, resolved));
+        }
+
+        let blob = self.get(reference, &format!("blobs/{digest}"), "*/*")?;
+        verify(&digest, &blob)?;
+
+        let dir = archive::unpack(blob.as_slice(), cache, &digest.replace(':', "-"))?;
+        Ok((dir, resolved))
+    }
+
+    /// Resolves floating `major` and `major.minor` tags to the highest published version they match
+    ///
+    /// @see: https://containers.dev/implementors/features-distribution/#versioning
+    pub fn resolve(&mut self, reference: &OciReference) -> Result<OciReference> {
+        let Some(tag) = reference.tag().filter(|_| reference.digest().is_none()) else {
+            return Ok(reference.clone());
+        };
+        // NOTE: exact versions and tags such as `latest` are used as is
+        let req = match VersionReq::parse(&format!("={tag}")) {
+            Ok(req) if Version::parse(tag).is_err() => req,
+            _ => return Ok(reference.clone()),
+        };
+        let version = self
+            .tags(reference)?
+            .iter()
+            .filter_map(|tag| Version::parse(tag).ok())
+            .filter(|version| req.matches(version))
+            .max()
+            .ok_or_else(|| Error::OciTagNotFound {
+                reference: reference.whole(),
+            })?;
+        Ok(OciReference::with_tag(
+            reference.registry().to_string(),
+            reference.repository().to_string(),
+            version.to_string(),
+        ))
+    }
+
+    /// Config of the image, for the Linux platform of the host when the image is multi-platform
+    ///
+    /// @see: https://github.com/opencontainers/image-spec/blob/main/image-index.md
+    pub fn image_config(&mut self, reference: &OciReference) -> Result<ImageConfig> {
+        #[derive(Deserialize)]
+        struct Manifest {
+            config: Option<Descriptor>,
+            #[serde(default)]
+            manifests: Vec<Descriptor>,
+        }
+        #[derive(Deserialize)]
+        struct Descriptor {
+            digest: String,
+            platform: Option<Platform>,
+        }
+        #[derive(Deserialize)]
+        struct Platform {
+            architecture: String,
+            os: String,
+        }
+        #[derive(Deserialize)]
+        struct Image {
+            #[serde(default)]
+            config: Option<ImageConfig>,
+        }
+
+        let target = reference.digest().or(reference.tag()).unwrap_or("latest");
+        let body = self.get(reference, &format!("manifests/{target}"), IMAGE_MEDIA_TYPES)?;
+        if let Some(expected) = reference.digest() {
+            verify(expected, &body)?;
+        }
+        let mut manifest = serde_json::from_slice::<Manifest>(&body)?;
+        if manifest.config.is_none() {
+            let architecture = architecture();
+            let digest = manifest
+                .manifests
+                .iter()
+                .find(|descriptor| {
+                    descriptor
+                        .platform
+                        .as_ref()
+                        .is_some_and(|platform| platform.os == "linux" && platform.architecture == architecture)
+                })
+                .map(|descriptor| descriptor.digest.clone())
+                .ok_or_else(|| Error::OciPlatformNotFound {
+                    platform: format!("linux/{architecture}"),
+                    reference: reference.whole(),
+                })?;
+            let body = self.get(reference, &format!("manifests/{digest}"), IMAGE_MEDIA_TYPES)?;
+            verify(&digest, &body)?;
+            manifest = serde_json::from_slice(&body)?;
+        }
+
+        let digest = manifest
+            .config
+            .ok_or_else(|| Error::OciPlatformNotFound {
+                platform: String::from("linux"),
+                reference: reference.whole(),
+            })?
+            .digest;
+        let body = self.get(reference, &format!("blobs/{digest}"), "*/*")?;
+        verify(&digest, &body)?;
+        Ok(serde_json::from_slice::<Image>(&body)?.config.unwrap_or_default())
+    }
+
+    /// @see: https://github.com/opencontainers/distribution-spec/blob/main/spec.md#listing-tags
+    pub fn tags(&mut self, reference: &OciReference) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Tags {
+            #[serde(default)]
+            tags: Vec<String>,
+        }
+        let body = self.get(reference, "tags/list", "application/json")?;
+        Ok(serde_json::from_slice::<Tags>(&body)?.tags)
+    }
+
+    /// Image manifest of `reference` and its digest
+    pub fn manifest(&mut self, reference: &OciReference) -> Result<(ImageManifest, String)> {
+        let target = reference.digest().or(reference.tag()).unwrap_or("latest");
+        let body = self.get(reference, &format!("manifests/{target}"), MANIFEST_MEDIA_TYPE)?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&body));
+        if let Some(expected) = reference.digest() {
+            verify(expected, &body)?;
+        }
+        Ok((serde_json::from_slice(&body)?, digest))
+    }
+
+    fn get(&mut self, reference: &OciReference, path: &str, accept: &str) -> Result<Vec<u8>> {
+        let registry = reference.resolve_registry();
+        let registry = if registry == "index.docker.io" {
+            "registry-1.docker.io"
+        } else {
+            registry
+        };
+        let url = format!("{}://{registry}/v2/{}/{path}", scheme(registry), reference.repository());
+
+        let mut retried = false;
+        loop {
+            let mut request = self.agent.get(&url).header("Accept", accept);
+            if let Some(token) = &self.token {
+                request = request.header("Authorization", token);
+            }
+            let mut response = request.call()?;
+            let status = response.status().as_u16();
+            if status == 401 && !retried {
+                let challenge = response
+                    .headers()
+                    .get("WWW-Authenticate")
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                self.token = Some(self.authenticate(reference.registry(), &challenge)?);
+                retried = true;
+                continue;
+            }
+            if !(200..300).contains(&status) {
+                return Err(Error::HttpStatus { status, url });
+            }
+            return Ok(response.body_mut().with_config().limit(u64::MAX).read_to_vec()?);
+        }
+    }
+
+    /// @see: https://distribution.github.io/distribution/spec/auth/token/
+    fn authenticate(&self, registry: &str, challenge: &str) -> Result<String> {
+        let credentials = self.config.credentials(registry)?;
+        let (scheme, params) = challenge.split_once(' ').unwrap_or((challenge, ""));
+        let params = parse_challenge(params);
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            let credentials = credentials.ok_or_else(|| Error::OciAuthInvalid {
+                registry: registry.to_string(),
+            })?;
+            let basic = BASE64.encode(format!("{}:{}", credentials.username, credentials.password));
+            return Ok(format!("Basic {basic}"));
+        }
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return Err(Error::OciAuthInvalid {
+                registry: registry.to_string(),
+            });
+        }
+
+        #[derive(Deserialize)]
+        struct Token {
+            access_token: Option<String>,
+            token: Option<String>,
+        }
+        let realm = params.get("realm").ok_or_else(|| Error::OciAuthInvalid {
+            registry: registry.to_string(),
+        })?;
+        let mut request = self.agent.get(realm);
+        for key in ["scope", "service"] {
+            if let Some(value) = params.get(key) {
+                request = request.query(key, value);
+            }
+        }
+        if let Some(credentials) = credentials {
+            let basic = BASE64.encode(format!("{}:{}", credentials.username, credentials.password));
+            request = request.header("Authorization", &format!("Basic {basic}"));
+        }
+        let mut response = request.call()?;
+        let status = response.status().as_u16();
+        if !(200..300).contains(&status) {
+            return Err(Error::HttpStatus {
+                status,
+                url: realm.clone(),
+            });
+        }
+        let token = serde_json::from_slice::<Token>(&response.body_mut().read_to_vec()?)?;
+        let token = token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| Error::OciAuthInvalid {
+                registry: registry.to_string(),
+            })?;
+        Ok(format!("Bearer {token}"))
+    }
+}
+
+/// `key="value",key=value` pairs of a `WWW-Authenticate` header
+fn parse_challenge(s: &str) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    let mut rest = s.trim();
+    while let Some((key, tail)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(tail) => tail.split_once('"').unwrap_or((tail, "")),
+            None => tail.split_once(',').unwrap_or((tail, "")),
+        };
+        params.insert(key, value.to_string());
+        rest = tail.trim_start_matches(',').trim();
+    }
+    params
+}
+
+/// Plain HTTP is only used for registries on the loopback interface
+fn scheme(registry: &str) -> &'static str {
+    let host = match registry.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => registry,
+    };
+    match host {
+        "localhost" | "127.0.0.1" | "[::1]" => "http",
+        _ => "https",
+    }
+}
+
+/// Architecture of the host, the way image indexes name it
+///
+/// @see: https://github.com/opencontainers/image-spec/blob/main/image-index.md#platform-variants
+fn architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "aarch64" => "arm64",
+        "x86_64" => "amd64",
+        arch => arch,
+    }
+}
+
+fn verify(digest: &str, data: &[u8]) -> Result<()> {
+    let got = format!("sha256:{:x}", Sha256::digest(data));
+    if got != digest {
+        return Err(Error::OciDigestMismatch {
+            expected: digest.to_string(),
+            got,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::tests::serve;
+
+    fn digest(data: &[u8]) -> String {
+        format!("sha256:{:x}", Sha256::digest(data))
+    }
+
+    fn layer() -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, data) in [
+            ("devcontainer-feature.json", r#"{"id":"foo","version":"1.0.0"}"#),
+            ("install.sh", "#!/bin/sh\n"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn routes(layer: &[u8]) -> (HashMap<String, Vec<u8>>, String) {
+        let config = b"{}".to_vec();
+        let manifest = serde_json::to_vec(&json!({
+            "schemaVersion": 2,
+            "mediaType": MANIFEST_MEDIA_TYPE,
+            "config": {
+                "mediaType": "application/vnd.devcontainers",
+                "digest": digest(&config),
+                "size": config.len(),
+            },
+            "layers": [{
+                "mediaType": LAYER_MEDIA_TYPE,
+                "digest": digest(layer),
+                "size": layer.len(),
+            }],
+        }))
+        .unwrap();
+        let manifest_digest = digest(&manifest);
+        let routes = HashMap::from([
+            (String::from("/v2/features/foo/manifests/1"), manifest.clone()),
+            (format!("/v2/features/foo/manifests/{manifest_digest}"), manifest),
+            (format!("/v2/features/foo/blobs/{}", digest(layer)), layer.to_vec()),
+        ]);
+        (routes, manifest_digest)
+    }
+
+    #[test]
+    fn pull() {
+        let (routes, manifest_digest) = routes(&layer());
+        let registry = serve(routes, None);
+        let cache = tempfile::tempdir().unwrap();
+
+        let reference = format!("{registry}/features/foo:1").parse().unwrap();
+        let (dir, resolved) = Registry::new(DockerConfig::default())
+            .pull(&reference, cache.path())
+            .unwrap();
+        assert!(dir.join("install.sh").exists());
+        assert!(dir.join("devcontainer-feature.json").exists());
+        assert_eq!(resolved, format!("{registry}/features/foo@{manifest_digest}"));
+
+        let reference = resolved.parse().unwrap();
+        let same = Registry::new(DockerConfig::default())
+            .pull(&reference, cache.path())
+            .unwrap();
+        assert_eq!((dir, resolved), same);
+    }
+
+    #[test]
+    fn pull_auth_basic() {
+        let (routes, _) = routes(&layer());
+        let expected = format!("Basic {}", BASE64.encode("user:pass"));
+        let registry = serve(routes, Some((String::from(r#"Basic realm="test""#), expected)));
+        let cache = tempfile::tempdir().unwrap();
+
+        let reference = format!("{registry}/features/foo:1").parse().unwrap();
+        match Registry::new(DockerConfig::default()).pull(&reference, cache.path()) {
+            Err(Error::OciAuthInvalid { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+
+        let config = serde_json::from_value::<DockerConfig>(json!({
+            "auths": { registry.clone(): { "auth": BASE64.encode("user:pass") } },
+        }))
+        .unwrap();
+        let (dir, _) = Registry::new(config).pull(&reference, cache.path()).unwrap();
+        assert!(dir.join("install.sh").exists());
+    }
+
+    #[test]
+    fn pull_auth_bearer() {
+        let (mut routes, _) = routes(&layer());
+        routes.insert(String::from("/token"), br#"{"token":"secret"}"#.to_vec());
+        // NOTE: the token endpoint lives on a separate host, like it does for most registries
+        let auth = serve(routes.clone(), None);
+        let challenge =
+            format!(r#"Bearer realm="http://{auth}/token",service="test",scope="repository:features/foo:pull""#);
+        let registry = serve(routes, Some((challenge, String::from("Bearer secret"))));
+        let cache = tempfile::tempdir().unwrap();
+
+        let reference = format!("{registry}/features/foo:1").parse().unwrap();
+        let (dir, _) = Registry::new(DockerConfig::default())
+            .pull(&reference, cache.path())
+            .unwrap();
+        assert!(dir.join("install.sh").exists());
+    }
+
+    #[test]
+    fn pull_digest_mismatch() {
+        let (mut routes, _) = routes(&layer());
+        for (route, body) in routes.iter_mut() {
+            if route.contains("/blobs/") {
+                body.extend(b"tampered");
+            }
+        }
+        let registry = serve(routes, None);
+        let cache = tempfile::tempdir().unwrap();
+
+        let reference = format!("{registry}/features/foo:1").parse().unwrap();
+        match Registry::new(DockerConfig::default()).pull(&reference, cache.path()) {
+            Err(Error::OciDigestMismatch { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let tags = json!({
+            "name": "features/foo",
+            "tags": ["1.0.0", "1.2.0", "1.10.1", "1.11.0-rc.1", "2.0.0", "latest"],
+        });
+        let routes = HashMap::from([(
+            String::from("/v2/features/foo/tags/list"),
+            serde_json::to_vec(&tags).unwrap(),
+        )]);
+        let registry = serve(routes, None);
+
+        let mut client = Registry::new(DockerConfig::default());
+        for (tag, expected) in [
+            ("1", "1.10.1"),
+            ("1.2", "1.2.0"),
+            ("2", "2.0.0"),
+            ("1.0.0", "1.0.0"),
+            ("latest", "latest"),
+        ] {
+            let reference = format!("{registry}/features/foo:{tag}").parse().unwrap();
+            let resolved = client.resolve(&reference).unwrap();
+            assert_eq!(resolved.tag(), Some(expected), "{tag}");
+            assert_eq!(resolved.repository(), "features/foo");
+        }
+
+        let reference = format!("{registry}/features/foo:3").parse().unwrap();
+        match client.resolve(&reference) {
+            Err(Error::OciTagNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn image_config() {
+        let config = serde_json::to_vec(&json!({
+            "architecture": "amd64",
+            "config": { "Cmd": ["bash"], "User": "vscode" },
+            "os": "linux",
+        }))
+        .unwrap();
+        let manifest = serde_json::to_vec(&json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+            "config": {
+                "mediaType": "application/vnd.docker.container.image.v1+json",
+                "digest": digest(&config),
+                "size": config.len(),
+            },
+            "layers": [],
+        }))
+        .unwrap();
+        let index = serde_json::to_vec(&json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.index.v1+json",
+            "manifests": [
+                {
+                    "digest": digest(b"other"),
+                    "platform": { "architecture": "s390x", "os": "linux" },
+                },
+                {
+                    "digest": digest(&manifest),
+                    "platform": { "architecture": architecture(), "os": "linux" },
+                },
+            ],
+        }))
+        .unwrap();
+        let routes = HashMap::from([
+            (String::from("/v2/library/debian/manifests/13"), index),
+            (String::from("/v2/library/debian/manifests/plain"), manifest.clone()),
+            (format!("/v2/library/debian/manifests/{}", digest(&manifest)), manifest),
+            (format!("/v2/library/debian/blobs/{}", digest(&config)), config),
+        ]);
+        let registry = serve(routes, None);
+
+        let mut client = Registry::new(DockerConfig::default());
+        for tag in ["13", "plain"] {
+            let reference = format!("{registry}/library/debian:{tag}").parse().unwrap();
+            let config = client.image_config(&reference).unwrap();
+            assert_eq!(config.cmd, Some(vec![String::from("bash")]), "{tag}");
+            assert_eq!(
+                (config.entrypoint, config.user.as_deref()),
+                (None, Some("vscode")),
+                "{tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_challenge() {
+        let params = super::parse_challenge(r#"realm="https://auth.example/token",service=registry,scope="a:b:pull""#);
+        assert_eq!(params["realm"], "https://auth.example/token");
+        assert_eq!(params["service"], "registry");
+        assert_eq!(params["scope"], "a:b:pull");
+    }
+}
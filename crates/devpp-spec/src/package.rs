@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::fs::remove_dir_all;
+use std::fs::write;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use oci_spec::image::ANNOTATION_REF_NAME;
+use oci_spec::image::ANNOTATION_TITLE;
+use oci_spec::image::Descriptor;
+use oci_spec::image::DescriptorBuilder;
+use oci_spec::image::ImageIndexBuilder;
+use oci_spec::image::ImageManifestBuilder;
+use oci_spec::image::MediaType;
+use oci_spec::image::OciLayoutBuilder;
+use semver::Version;
+use serde_json::Value;
+use serde_json::json;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::feat::Feature;
+use crate::feat::LAYER_MEDIA_TYPE;
+use crate::feat::Reference;
+use crate::feat::ReferenceKind;
+
+/// @see: https://containers.dev/implementors/features-distribution/#oci-registry
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.devcontainers";
+
+/// Packages every feature in `src` into `output` and returns them in the order they were packaged
+///
+/// `src` is either a feature folder or a folder of them. Each feature is archived as
+/// `devcontainer-feature-<id>.tgz` and listed in `devcontainer-collection.json`, and is also written
+/// as an OCI image layout under `layout/<id>/` when `layout` is given.
+///
+/// @see: https://containers.dev/implementors/features-distribution/#packaging
+pub fn package(src: &Path, output: &Path, layout: Option<&Path>) -> Result<Vec<Feature>> {
+    let dirs = find_features(src)?;
+    if dirs.is_empty() {
+        return Err(Error::PackageNotFound {
+            path: src.to_path_buf(),
+        });
+    }
+
+    create_dir_all(output)?;
+    let mut features = vec![];
+    let mut metadata = vec![];
+    for dir in dirs {
+        let reference = Reference {
+            id: dir.to_string_lossy().into_owned(),
+            kind: ReferenceKind::Local { path: dir.clone() },
+        };
+        let feature = Feature::new(&reference, None)?;
+        let mut s = read_to_string(&feature.metadata)?;
+        json_strip_comments::strip(&mut s)?;
+        let value = serde_json::from_str::<Value>(&s)?;
+        let layer = archive(&dir)?;
+
+        let mut tgz = GzEncoder::new(vec![], Compression::default());
+        tgz.write_all(&layer)?;
+        write(
+            output.join(format!("devcontainer-feature-{}.tgz", feature.inner.id)),
+            tgz.finish()?,
+        )?;
+        if let Some(layout) = layout {
+            write_layout(&layout.join(&feature.inner.id), &feature, &value, &layer)?;
+        }
+        metadata.push(value);
+        features.push(feature);
+    }
+
+    // @see: https://containers.dev/implementors/features-distribution/#devcontainer-collectionjson
+    let collection = json!({
+        "sourceInformation": { "source": "devpp" },
+        "features": metadata,
+    });
+    write(
+        output.join("devcontainer-collection.json"),
+        serde_json::to_string_pretty(&collection)? + "\n",
+    )?;
+    Ok(features)
+}
+
+/// `src` itself when it is a feature folder, its feature sub-folders otherwise
+fn find_features(src: &Path) -> Result<Vec<PathBuf>> {
+    let src = src.canonicalize()?;
+    if src.join("devcontainer-feature.json").try_exists()? {
+        return Ok(vec![src]);
+    }
+    let mut dirs = vec![];
+    for entry in read_dir(&src)? {
+        let path = entry?.path();
+        if path.join("devcontainer-feature.json").try_exists()? {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Plain tar of the feature folder, identical for identical contents
+fn archive(dir: &Path) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(vec![]);
+    builder.mode(tar::HeaderMode::Deterministic);
+    builder.follow_symlinks(false);
+    append_dir(&mut builder, dir, dir)?;
+    Ok(builder.into_inner()?)
+}
+
+fn append_dir(builder: &mut tar::Builder<Vec<u8>>, root: &Path, dir: &Path) -> Result<()> {
+    let mut entries = read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        builder.append_path_with_name(&path, path.strip_prefix(root)?)?;
+        if entry.file_type()?.is_dir() {
+            append_dir(builder, root, &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the feature as an OCI image layout, tagged the way `devcontainer features publish` tags it
+///
+/// @see: https://github.com/opencontainers/image-spec/blob/main/image-layout.md
+fn write_layout(dir: &Path, feature: &Feature, metadata: &Value, layer: &[u8]) -> Result<()> {
+    if dir.try_exists()? {
+        remove_dir_all(dir)?;
+    }
+    create_dir_all(dir.join("blobs").join("sha256"))?;
+
+    let config = put_blob(dir, MediaType::Other(CONFIG_MEDIA_TYPE.to_string()), b"{}")?;
+    let mut layer = put_blob(dir, MediaType::Other(LAYER_MEDIA_TYPE.to_string()), layer)?;
+    layer.set_annotations(Some(HashMap::from([(
+        ANNOTATION_TITLE.to_string(),
+        format!("devcontainer-feature-{}.tgz", feature.inner.id),
+    )])));
+
+    let manifest = ImageManifestBuilder::default()
+        .schema_version(2u32)
+        .media_type(MediaType::ImageManifest)
+        .config(config)
+        .layers(vec![layer])
+        .annotations(HashMap::from([
+            (
+                String::from("com.github.package.type"),
+                String::from("devcontainer_feature"),
+            ),
+            (
+                String::from("dev.containers.metadata"),
+                serde_json::to_string(metadata)?,
+            ),
+        ]))
+        .build()?;
+    // NOTE: annotations are hash maps, go through `Value` to sort their keys and keep the digest stable
+    let manifest = put_blob(
+        dir,
+        MediaType::ImageManifest,
+        &serde_json::to_vec(&serde_json::to_value(&manifest)?)?,
+    )?;
+
+    let manifests = tags(&feature.inner.version)
+        .into_iter()
+        .map(|tag| {
+            let mut descriptor = manifest.clone();
+            descriptor.set_annotations(Some(HashMap::from([(ANNOTATION_REF_NAME.to_string(), tag)])));
+            descriptor
+        })
+        .collect::<Vec<_>>();
+    let index = ImageIndexBuilder::default()
+        .schema_version(2u32)
+        .media_type(MediaType::ImageIndex)
+        .manifests(manifests)
+        .build()?;
+    write(
+        dir.join("index.json"),
+        serde_json::to_vec(&serde_json::to_value(&index)?)?,
+    )?;
+
+    let layout = OciLayoutBuilder::default().image_layout_version("1.0.0").build()?;
+    write(dir.join("oci-layout"), serde_json::to_vec(&layout)?)?;
+    Ok(())
+}
+
+fn put_blob(dir: &Path, media_type: MediaType, data: &[u8]) -> Result<Descriptor> {
+    let hex = format!("{:x}", Sha256::digest(data));
+    write(dir.join("blobs").join("sha256").join(&hex), data)?;
+    Ok(DescriptorBuilder::default()
+        .media_type(media_type)
+        .digest(format!("sha256:{hex}").parse::<oci_spec::image::Digest>()?)
+        .size(data.len() as u64)
+        .build()?)
+}
+
+/// `1`, `1.2`, `1.2.3` and `latest` for `1.2.3`, the version as is and `latest` otherwise
+///
+/// @see: https://containers.dev/implementors/features-distribution/#versioning
+fn tags(version: &str) -> Vec<String> {
+    match Version::parse(version) {
+        Ok(v) if v.pre.is_empty() && v.build.is_empty() => vec![
+            v.major.to_string(),
+            format!("{}.{}", v.major, v.minor),
+            v.to_string(),
+            String::from("latest"),
+        ],
+        _ => vec![version.to_string(), String::from("latest")],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::root;
+
+    #[test]
+    fn package() {
+        let output = tempfile::tempdir().unwrap();
+        let (out, layout) = (output.path().join("output"), output.path().join("layout"));
+        let features = super::package(&root("tests/fixtures/package/src"), &out, Some(&layout)).unwrap();
+        let ids = features
+            .iter()
+            .map(|feature| feature.inner.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["bar", "foo"]);
+
+        let collection =
+            serde_json::from_str::<Value>(&read_to_string(out.join("devcontainer-collection.json")).unwrap()).unwrap();
+        assert_eq!(collection["features"][1]["id"], "foo");
+
+        let tgz = std::fs::read(out.join("devcontainer-feature-foo.tgz")).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tgz.as_slice()));
+        let mut paths = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, ["devcontainer-feature.json", "install.sh"]);
+
+        let index = read_to_string(layout.join("foo").join("index.json")).unwrap();
+        let refs = serde_json::from_str::<Value>(&index).unwrap()["manifests"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|manifest| {
+                manifest["annotations"][ANNOTATION_REF_NAME]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(refs, ["1", "1.2", "1.2.3", "latest"]);
+
+        // NOTE: packaging the same contents again yields the same digests
+        let again = tempfile::tempdir().unwrap();
+        super::package(
+            &root("tests/fixtures/package/src"),
+            &again.path().join("output"),
+            Some(&again.path().join("layout")),
+        )
+        .unwrap();
+        assert_eq!(
+            index,
+            read_to_string(again.path().join("layout/foo/index.json")).unwrap()
+        );
+    }
+
+    #[test]
+    fn package_not_found() {
+        let output = tempfile::tempdir().unwrap();
+        match super::package(&root("tests/fixtures/package"), output.path(), None) {
+            Err(Error::PackageNotFound { .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+
+    /// The layout is served the way a registry serves a pushed feature, and consumed by [`crate::oci::Registry`]
+    #[cfg(feature = "artifact")]
+    #[test]
+    fn package_pull() {
+        use crate::oci::DockerConfig;
+        use crate::oci::Registry;
+        use crate::tests::serve;
+
+        let output = tempfile::tempdir().unwrap();
+        let layout = output.path().join("layout");
+        super::package(
+            &root("tests/fixtures/package/src/foo"),
+            &output.path().join("output"),
+            Some(&layout),
+        )
+        .unwrap();
+
+        let dir = layout.join("foo");
+        let index = serde_json::from_str::<Value>(&read_to_string(dir.join("index.json")).unwrap()).unwrap();
+        let mut routes = HashMap::new();
+        for entry in read_dir(dir.join("blobs").join("sha256")).unwrap() {
+            let path = entry.unwrap().path();
+            let digest = format!("sha256:{}", path.file_name().unwrap().to_string_lossy());
+            let blob = std::fs::read(&path).unwrap();
+            routes.insert(format!("/v2/features/foo/blobs/{digest}"), blob.clone());
+            routes.insert(format!("/v2/features/foo/manifests/{digest}"), blob);
+        }
+        for manifest in index["manifests"].as_array().unwrap() {
+            let tag = manifest["annotations"][ANNOTATION_REF_NAME].as_str().unwrap();
+            let digest = manifest["digest"].as_str().unwrap();
+            let blob = routes[&format!("/v2/features/foo/blobs/{digest}")].clone();
+            routes.insert(format!("/v2/features/foo/manifests/{tag}"), blob);
+        }
+        let registry = serve(routes, None);
+
+        let cache = tempfile::tempdir().unwrap();
+        let reference = format!("{registry}/features/foo:1").parse().unwrap();
+        let (dir, _) = Registry::new(DockerConfig::default())
+            .pull(&reference, cache.path())
+            .unwrap();
+        assert!(dir.join("install.sh").exists());
+        assert!(dir.join("devcontainer-feature.json").exists());
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use flate2::read::GzDecoder;
+use sha2::Digest;
+use sha2::Sha256;
+use url::Url;
+
+use crate::archive;
+use crate::error::Error;
+use crate::error::Result;
+
+/// @see: https://www.rfc-editor.org/rfc/rfc1952#section-2.3.1
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Downloads a `.tgz` feature into `cache` and returns the directory it was extracted to, along with the URL
+///
+/// @see: https://containers.dev/implementors/features-distribution/#directly-reference-tarball
+pub fn fetch(url: &Url, cache: &Path) -> Result<(PathBuf, String)> {
+    let agent: ureq::Agent = ureq::Agent::config_builder().http_status_as_error(false).build().into();
+    let mut response = agent.get(url.as_str()).call()?;
+    let status = response.status().as_u16();
+    if !(200..300).contains(&status) {
+        return Err(Error::HttpStatus {
+            status,
+            url: url.to_string(),
+        });
+    }
+    let data = response.body_mut().with_config().limit(u64::MAX).read_to_vec()?;
+
+    let name = format!("sha256-{:x}", Sha256::digest(&data));
+    let dir = cache.join(&name);
+    if !dir.try_exists()? {
+        // NOTE: some publishers ship plain tar archives under the .tgz name
+        match data.starts_with(&GZIP_MAGIC) {
+            true => archive::unpack(GzDecoder::new(data.as_slice()), cache, &name)?,
+            false => archive::unpack(data.as_slice(), cache, &name)?,
+        };
+    }
+    Ok((dir, url.to_string()))
+}
+
+/// Feature id encoded in the `devcontainer-feature-<id>.tgz` file name
+pub fn feature_id(url: &Url) -> Option<&str> {
+    url.path_segments()?
+        .next_back()?
+        .strip_prefix("devcontainer-feature-")?
+        .strip_suffix(".tgz")
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+    use crate::tests::serve;
+
+    fn tar(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn tgz(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&tar(entries)).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn feature_id() {
+        let url = |s| Url::parse(s).unwrap();
+        assert_eq!(
+            super::feature_id(&url("https://example.com/devcontainer-feature-foo.tgz")),
+            Some("foo")
+        );
+        assert_eq!(
+            super::feature_id(&url("https://example.com/devcontainer-feature-.tgz")),
+            None
+        );
+        assert_eq!(super::feature_id(&url("https://example.com/foo.tgz")), None);
+    }
+
+    #[test]
+    fn fetch() {
+        let data = tgz(&[
+            ("devcontainer-feature.json", r#"{"id":"foo","version":"1.0.0"}"#),
+            ("install.sh", "#!/bin/sh\n"),
+        ]);
+        let host = serve(
+            HashMap::from([("/devcontainer-feature-foo.tgz".to_string(), data)]),
+            None,
+        );
+        let cache = tempfile::tempdir().unwrap();
+        let url = Url::parse(&format!("http://{host}/devcontainer-feature-foo.tgz")).unwrap();
+        let (dir, resolved) = super::fetch(&url, cache.path()).unwrap();
+        assert_eq!(resolved, url.as_str());
+        assert!(dir.starts_with(cache.path()));
+        assert!(dir.join("devcontainer-feature.json").exists());
+        assert!(dir.join("install.sh").exists());
+        assert_eq!(super::fetch(&url, cache.path()).unwrap().0, dir);
+    }
+
+    #[test]
+    fn fetch_plain() {
+        let data = tar(&[("install.sh", "#!/bin/sh\n")]);
+        let host = serve(
+            HashMap::from([("/devcontainer-feature-foo.tgz".to_string(), data)]),
+            None,
+        );
+        let cache = tempfile::tempdir().unwrap();
+        let url = Url::parse(&format!("http://{host}/devcontainer-feature-foo.tgz")).unwrap();
+        assert!(super::fetch(&url, cache.path()).unwrap().0.join("install.sh").exists());
+    }
+
+    #[test]
+    fn fetch_status() {
+        let host = serve(HashMap::new(), None);
+        let cache = tempfile::tempdir().unwrap();
+        let url = Url::parse(&format!("http://{host}/devcontainer-feature-foo.tgz")).unwrap();
+        match super::fetch(&url, cache.path()) {
+            Err(Error::HttpStatus { status: 404, .. }) => {}
+            other => panic!("{other:?}"),
+        }
+    }
+}
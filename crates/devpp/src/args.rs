@@ -19,6 +19,16 @@ impl Default for Args {
 pub enum CommandKind {
     /// Build a dev container image
     Build(cmd::build::Args),
+    /// Manage features downloaded from registries and URLs
+    Cache(cmd::cache::Args),
     /// Generate tab-completion scripts for your shell
     Completion(cmd::completion::Args),
+    /// Inspect the effective configuration
+    Config(cmd::config::Args),
+    /// Author features
+    Features(cmd::features::Args),
+    /// Manage devcontainer-lock.json
+    Lock(cmd::lock::Args),
+    /// Copy remote features into .devcontainer/vendor/ for offline builds
+    Vendor(cmd::vendor::Args),
 }
@@ -1,5 +1,9 @@
+use std::fs::File;
 use std::path::PathBuf;
 
+use devpp_core::Lock;
+use devpp_core::image::DefaultInspector;
+
 use crate::error::Result;
 
 #[derive(clap::Args)]
@@ -8,6 +12,17 @@ pub struct Args {
     /// devcontainer.json path
     #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
     pub config: Option<PathBuf>,
+    // @see: https://github.com/compose-spec/compose-spec/blob/main/13-merge.md
+    /// Write a compose override file pointing the service at the generated Containerfile
+    #[arg(long, requires = "output", value_hint = clap::ValueHint::FilePath)]
+    pub compose_override: Option<PathBuf>,
+    // @see: https://github.com/devcontainers/spec/blob/main/proposals/devcontainer-lockfile.md
+    /// Fail instead of updating devcontainer-lock.json when features resolve differently
+    #[arg(long)]
+    pub frozen: bool,
+    /// Write the Containerfile to a file instead of stdout
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
     // @see: https://containers.dev/implementors/spec/#project-workspace-folder
     /// Project workspace folder (typically the root of the git repository)
     #[arg(default_value = ".", value_hint = clap::ValueHint::DirPath)]
@@ -15,9 +30,32 @@ pub struct Args {
 }
 
 pub fn run(args: Args) -> Result<()> {
-    Ok(devpp_core::build(
-        &mut std::io::stdout(),
-        &args.workspace,
-        args.config.as_deref(),
-    )?)
+    let lock = if args.frozen { Lock::Frozen } else { Lock::Update };
+    let mut inspector = DefaultInspector::default();
+    match &args.output {
+        Some(output) => devpp_core::build(
+            File::create(output)?,
+            &mut inspector,
+            &args.workspace,
+            args.config.as_deref(),
+            lock,
+        )?,
+        None => devpp_core::build(
+            &mut std::io::stdout(),
+            &mut inspector,
+            &args.workspace,
+            args.config.as_deref(),
+            lock,
+        )?,
+    }
+    if let (Some(compose_override), Some(output)) = (&args.compose_override, &args.output) {
+        devpp_core::compose_override(
+            File::create(compose_override)?,
+            &mut inspector,
+            &args.workspace,
+            args.config.as_deref(),
+            output,
+        )?;
+    }
+    Ok(())
 }
@@ -0,0 +1,58 @@
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use devpp_spec::cache::Cache;
+
+use crate::error::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: CommandKind,
+}
+
+#[derive(clap::Subcommand)]
+pub enum CommandKind {
+    /// Remove every cached feature
+    Clear,
+    /// List cached features and when they were fetched
+    List,
+    /// Remove features fetched more than `max-age` days ago
+    Prune {
+        #[arg(long, default_value_t = 30)]
+        max_age: u64,
+    },
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let cache = Cache::new()?;
+    match args.command {
+        CommandKind::Clear => cache.clear()?,
+        CommandKind::List => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            for (reference, record) in cache.list()? {
+                let age = now.saturating_sub(record.fetched);
+                println!("{}\t{}\t{reference}", format_age(age), record.digest);
+            }
+        }
+        CommandKind::Prune { max_age } => {
+            for reference in cache.prune(Duration::from_secs(max_age * 24 * 60 * 60))? {
+                println!("{reference}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
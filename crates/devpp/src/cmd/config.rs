@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use devpp_core::image::DefaultInspector;
+
+use crate::error::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: CommandKind,
+}
+
+#[derive(clap::Subcommand)]
+pub enum CommandKind {
+    // @see: https://containers.dev/implementors/spec/#merge-logic
+    /// Print the configuration the built image adds up to, once its metadata is merged
+    Resolved {
+        // @see: https://containers.dev/implementors/spec/#devcontainerjson
+        /// devcontainer.json path
+        #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+        config: Option<PathBuf>,
+        // @see: https://containers.dev/implementors/spec/#project-workspace-folder
+        /// Project workspace folder (typically the root of the git repository)
+        #[arg(long, default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        workspace: PathBuf,
+    },
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        CommandKind::Resolved { config, workspace } => {
+            let mut inspector = DefaultInspector::default();
+            devpp_core::config_resolved(&mut std::io::stdout(), &mut inspector, &workspace, config.as_deref())?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use devpp_core::image::DefaultInspector;
+use devpp_core::scenario::CommandBuilder;
+
+use crate::error::Error;
+use crate::error::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: CommandKind,
+}
+
+#[derive(clap::Subcommand)]
+pub enum CommandKind {
+    // @see: https://containers.dev/implementors/features-distribution/#packaging
+    /// Package a feature folder, or a folder of them, into .tgz archives and a devcontainer-collection.json
+    #[cfg(feature = "package")]
+    Package {
+        /// Feature folder, or a folder containing feature folders (typically src/)
+        #[arg(value_hint = clap::ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Also write every feature as an OCI image layout in <OCI_LAYOUT>/<id>/, e.g. for `oras cp --from-oci-layout`
+        #[arg(long, value_hint = clap::ValueHint::DirPath)]
+        oci_layout: Option<PathBuf>,
+        /// Folder the archives and the collection are written to
+        #[arg(short, long, default_value = "./output", value_hint = clap::ValueHint::DirPath)]
+        output: PathBuf,
+    },
+    // @see: https://github.com/devcontainers/cli/blob/main/docs/features/test.md
+    /// Build the default test and every scenario in test/<feature>/, and report which ones pass
+    Test {
+        /// Image the default test installs the feature on
+        #[arg(long, default_value = "ubuntu:focal")]
+        base_image: String,
+        /// Command the generated Containerfile and build context are appended to, as `-f <file> <context>`
+        #[arg(long, default_value = "docker build")]
+        builder: String,
+        /// Feature id, i.e. the folder name under src/ and test/
+        feature: String,
+        /// Collection folder holding src/ and test/
+        #[arg(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        collection: PathBuf,
+    },
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        #[cfg(feature = "package")]
+        CommandKind::Package {
+            dir,
+            oci_layout,
+            output,
+        } => {
+            for feature in devpp_spec::package::package(&dir, &output, oci_layout.as_deref())? {
+                println!("{}\t{}", feature.inner.id, feature.inner.version);
+            }
+        }
+        CommandKind::Test {
+            base_image,
+            builder,
+            feature,
+            collection,
+        } => {
+            let mut builder = CommandBuilder::new(&builder);
+            let mut inspector = DefaultInspector::default();
+            let outcomes = devpp_core::test_feature(&mut builder, &mut inspector, &collection, &feature, &base_image)?;
+            let mut failed = vec![];
+            for outcome in outcomes {
+                println!("{}\t{}", if outcome.passed { "PASS" } else { "FAIL" }, outcome.name);
+                if !outcome.passed {
+                    failed.push(outcome.name);
+                }
+            }
+            if !failed.is_empty() {
+                return Err(Error::FeatureTestFailed { failed });
+            }
+        }
+    }
+    Ok(())
+}
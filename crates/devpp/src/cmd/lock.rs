@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use crate::error::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: CommandKind,
+}
+
+#[derive(clap::Subcommand)]
+pub enum CommandKind {
+    /// Re-resolve a feature, or every feature, and rewrite devcontainer-lock.json
+    Update {
+        // @see: https://containers.dev/implementors/spec/#devcontainerjson
+        /// devcontainer.json path
+        #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+        config: Option<PathBuf>,
+        /// Feature reference as written in the lockfile, the version may be omitted
+        feature: Option<String>,
+        // @see: https://containers.dev/implementors/spec/#project-workspace-folder
+        /// Project workspace folder (typically the root of the git repository)
+        #[arg(long, default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        workspace: PathBuf,
+    },
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        CommandKind::Update {
+            config,
+            feature,
+            workspace,
+        } => devpp_core::lock_update(&workspace, config.as_deref(), feature.as_deref())?,
+    }
+    Ok(())
+}
@@ -1,2 +1,7 @@
 pub mod build;
+pub mod cache;
 pub mod completion;
+pub mod config;
+pub mod features;
+pub mod lock;
+pub mod vendor;
@@ -0,0 +1,52 @@
+use std::fs::write;
+use std::path::PathBuf;
+
+use crate::error::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Only fail when vendored features are stale, without fetching anything
+    #[arg(long, conflicts_with = "output")]
+    pub check: bool,
+    // @see: https://containers.dev/implementors/spec/#devcontainerjson
+    /// devcontainer.json path
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    pub config: Option<PathBuf>,
+    /// Write the rewritten config to a file instead of stdout
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+    // @see: https://containers.dev/implementors/spec/#project-workspace-folder
+    /// Project workspace folder (typically the root of the git repository)
+    #[arg(default_value = ".", value_hint = clap::ValueHint::DirPath)]
+    pub workspace: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    if args.check {
+        devpp_core::vendor_check(&args.workspace, args.config.as_deref())?;
+        return Ok(());
+    }
+    let stale = match &args.output {
+        Some(output) => {
+            // NOTE: render first, an empty config at `output` would be picked up while resolving the features
+            let dir = output.parent().filter(|dir| !dir.as_os_str().is_empty());
+            let mut config = vec![];
+            let stale = devpp_core::vendor(
+                &mut config,
+                &args.workspace,
+                args.config.as_deref(),
+                Some(dir.unwrap_or(".".as_ref())),
+            )?;
+            write(output, config)?;
+            stale
+        }
+        None => devpp_core::vendor(&mut std::io::stdout(), &args.workspace, args.config.as_deref(), None)?,
+    };
+    for stale in stale {
+        eprintln!(
+            "warning: {:?} is stale, it was vendored from {:?}",
+            stale.path, stale.reference
+        );
+    }
+    Ok(())
+}
@@ -2,6 +2,12 @@
 pub enum Error {
     #[error(transparent)]
     DevppCore(#[from] devpp_core::error::Error),
+    #[error(transparent)]
+    DevppSpec(#[from] devpp_spec::error::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("scenarios failed: {}", failed.join(", "))]
+    FeatureTestFailed { failed: Vec<String> },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
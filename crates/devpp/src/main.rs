@@ -11,6 +11,11 @@ fn main() -> Result<()> {
     let args = Args::default();
     match args.command {
         CommandKind::Build(args) => cmd::build::run(args),
+        CommandKind::Cache(args) => cmd::cache::run(args),
         CommandKind::Completion(args) => cmd::completion::run::<Args>(args),
+        CommandKind::Config(args) => cmd::config::run(args),
+        CommandKind::Features(args) => cmd::features::run(args),
+        CommandKind::Lock(args) => cmd::lock::run(args),
+        CommandKind::Vendor(args) => cmd::vendor::run(args),
     }
 }
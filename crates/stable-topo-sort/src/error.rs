@@ -1,7 +1,10 @@
 #[derive(Debug, thiserror::Error)]
-pub enum Error {
-    #[error("Graph has at least one cycle")]
-    CycleDetected,
+pub enum Error<T> {
+    /// Nodes along the cycle in edge direction, the first node is repeated at the end
+    #[error("Graph has at least one cycle: {cycle:?}")]
+    CycleDetected { cycle: Vec<T> },
+    #[error("Edge {from:?} -> {to:?} references an unknown node")]
+    DanglingEdge { from: T, to: T },
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T, N> = std::result::Result<T, Error<N>>;
@@ -1,35 +1,123 @@
 pub mod error;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::hash::Hash;
 
 use crate::error::Error;
 use crate::error::Result;
 
+/// How to treat edges that reference nodes missing from `nodes`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Dangling {
+    /// Fail with [`Error::DanglingEdge`]
+    #[default]
+    Error,
+    /// Drop the edge
+    Ignore,
+}
+
 /// @see: https://en.wikipedia.org/wiki/Topological_sorting#Depth-first_search
-pub fn stable_topo_sort<T>(nodes: &[T], edges: &[(T, T)]) -> Result<Vec<T>>
+pub fn stable_topo_sort<T>(nodes: &[T], edges: &[(T, T)], dangling: Dangling) -> Result<Vec<T>, T>
 where
     T: Clone + Eq + Hash,
 {
+    let incoming = incoming(nodes, edges, dangling)?;
+    let mut marks = HashMap::new();
     let mut result = Vec::with_capacity(nodes.len());
-    let mut ctx = Context {
-        incoming: &edges.iter().fold(Default::default(), |mut acc, (from, to)| {
-            acc.entry(to).or_default().push(from);
-            acc
-        }),
-        marks: &mut Default::default(),
-        result: &mut result,
-    };
     for node in nodes {
-        visit(&mut ctx, node)?;
+        visit(&incoming, &mut marks, &mut result, node)?;
     }
     Ok(result)
 }
 
-struct Context<'a, T> {
-    incoming: &'a HashMap<&'a T, Vec<&'a T>>,
-    marks: &'a mut HashMap<&'a T, Mark>,
-    result: &'a mut Vec<T>,
+/// Groups nodes into levels (antichains), every node only depends on nodes from the previous levels
+pub fn stable_topo_levels<T>(nodes: &[T], edges: &[(T, T)], dangling: Dangling) -> Result<Vec<Vec<T>>, T>
+where
+    T: Clone + Eq + Hash,
+{
+    let order = stable_topo_sort(nodes, edges, dangling)?;
+    let (levels, _) = longest_paths(&order, &incoming(nodes, edges, dangling)?);
+    let mut result = Vec::<Vec<T>>::new();
+    for node in &order {
+        let level = levels[node];
+        if result.len() <= level {
+            result.resize_with(level + 1, Vec::new);
+        }
+        result[level].push(node.clone());
+    }
+    Ok(result)
+}
+
+/// Longest chain of nodes, i.e. the lower bound of sequential steps
+pub fn critical_path<T>(nodes: &[T], edges: &[(T, T)], dangling: Dangling) -> Result<Vec<T>, T>
+where
+    T: Clone + Eq + Hash,
+{
+    let order = stable_topo_sort(nodes, edges, dangling)?;
+    let (levels, prev) = longest_paths(&order, &incoming(nodes, edges, dangling)?);
+    let mut end = None;
+    for node in &order {
+        if end.is_none_or(|end| levels[node] > levels[end]) {
+            end = Some(node);
+        }
+    }
+    let Some(mut node) = end else {
+        return Ok(vec![]);
+    };
+    let mut result = vec![node.clone()];
+    while let Some(dep) = prev.get(node) {
+        result.push((*dep).clone());
+        node = dep;
+    }
+    result.reverse();
+    Ok(result)
+}
+
+/// Incoming edges of every node, in the order they were given
+fn incoming<'a, T>(nodes: &'a [T], edges: &'a [(T, T)], dangling: Dangling) -> Result<HashMap<&'a T, Vec<&'a T>>, T>
+where
+    T: Clone + Eq + Hash,
+{
+    let known = nodes.iter().collect::<HashSet<_>>();
+    let mut incoming = HashMap::<&T, Vec<&T>>::new();
+    for (from, to) in edges {
+        if !(known.contains(from) && known.contains(to)) {
+            match dangling {
+                Dangling::Error => {
+                    return Err(Error::DanglingEdge {
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                }
+                Dangling::Ignore => continue,
+            }
+        }
+        incoming.entry(to).or_default().push(from);
+    }
+    Ok(incoming)
+}
+
+/// Level of every node and its predecessor along the longest path, `order` must be topologically sorted
+fn longest_paths<'a, T>(
+    order: &'a [T],
+    incoming: &HashMap<&'a T, Vec<&'a T>>,
+) -> (HashMap<&'a T, usize>, HashMap<&'a T, &'a T>)
+where
+    T: Eq + Hash,
+{
+    let (mut levels, mut prev) = (HashMap::new(), HashMap::new());
+    for node in order {
+        let mut level = 0;
+        for dep in incoming.get(node).into_iter().flatten() {
+            if levels[dep] + 1 > level {
+                level = levels[dep] + 1;
+                prev.insert(node, *dep);
+            }
+        }
+        levels.insert(node, level);
+    }
+    (levels, prev)
 }
 
 enum Mark {
@@ -37,22 +125,119 @@ enum Mark {
     Temporary,
 }
 
-fn visit<'a, T>(ctx: &mut Context<'a, T>, node: &'a T) -> Result<()>
+/// Iterative depth-first search, so that deep graphs don't overflow the call stack
+fn visit<'a, T>(
+    incoming: &HashMap<&'a T, Vec<&'a T>>,
+    marks: &mut HashMap<&'a T, Mark>,
+    result: &mut Vec<T>,
+    node: &'a T,
+) -> Result<(), T>
 where
     T: Clone + Eq + Hash,
 {
-    match ctx.marks.get(node) {
-        Some(Mark::Permanent) => return Ok(()),
-        Some(Mark::Temporary) => return Err(Error::CycleDetected),
-        None => {}
-    }
-    ctx.marks.insert(node, Mark::Temporary);
-    if let Some(deps) = ctx.incoming.get(node) {
-        for dep in deps {
-            visit(ctx, dep)?;
+    if marks.contains_key(node) {
+        return Ok(());
+    }
+    marks.insert(node, Mark::Temporary);
+    let mut stack = vec![(node, 0)];
+    while let Some((node, index)) = stack.last_mut() {
+        let Some(dep) = incoming.get(*node).and_then(|deps| deps.get(*index)).copied() else {
+            marks.insert(*node, Mark::Permanent);
+            result.push((*node).clone());
+            stack.pop();
+            continue;
+        };
+        *index += 1;
+        match marks.get(dep) {
+            Some(Mark::Permanent) => {}
+            Some(Mark::Temporary) => {
+                let start = stack
+                    .iter()
+                    .position(|(other, _)| *other == dep)
+                    .expect("node is on the stack");
+                let mut cycle = stack[start..]
+                    .iter()
+                    .map(|(node, _)| (*node).clone())
+                    .collect::<Vec<_>>();
+                cycle.push(dep.clone());
+                // NOTE: the stack follows incoming edges, so reverse it to match the edge direction
+                cycle.reverse();
+                return Err(Error::CycleDetected { cycle });
+            }
+            None => {
+                marks.insert(dep, Mark::Temporary);
+                stack.push((dep, 0));
+            }
         }
     }
-    ctx.marks.insert(node, Mark::Permanent);
-    ctx.result.push(node.clone());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_detected() {
+        let nodes = ["a", "b", "c", "d"];
+        let edges = [("a", "b"), ("b", "c"), ("c", "d"), ("d", "b")];
+        match stable_topo_sort(&nodes, &edges, Dangling::Error) {
+            Err(Error::CycleDetected { cycle }) => assert_eq!(cycle, ["b", "c", "d", "b"]),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn critical_path() {
+        let nodes = ["a", "b", "c", "d", "e"];
+        let edges = [("a", "d"), ("b", "c"), ("c", "d"), ("d", "e")];
+        assert_eq!(
+            super::critical_path(&nodes, &edges, Dangling::Error).unwrap(),
+            ["b", "c", "d", "e"]
+        );
+        assert_eq!(
+            super::critical_path::<&str>(&[], &[], Dangling::Error).unwrap(),
+            [] as [&str; 0]
+        );
+    }
+
+    #[test]
+    fn dangling() {
+        let nodes = ["a", "b"];
+        let edges = [("x", "b"), ("a", "b")];
+        match stable_topo_sort(&nodes, &edges, Dangling::Error) {
+            Err(Error::DanglingEdge { from, to }) => assert_eq!((from, to), ("x", "b")),
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(stable_topo_sort(&nodes, &edges, Dangling::Ignore).unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn deep() {
+        let nodes = (0..100_000).collect::<Vec<_>>();
+        let edges = nodes.windows(2).map(|pair| (pair[1], pair[0])).collect::<Vec<_>>();
+        let result = stable_topo_sort(&nodes, &edges, Dangling::Error).unwrap();
+        assert_eq!(result.first(), nodes.last());
+        assert_eq!(result.last(), nodes.first());
+    }
+
+    #[test]
+    fn levels() {
+        let nodes = ["e", "d", "c", "b", "a"];
+        let edges = [("a", "d"), ("b", "c"), ("c", "d"), ("d", "e")];
+        assert_eq!(
+            stable_topo_levels(&nodes, &edges, Dangling::Error).unwrap(),
+            [vec!["a", "b"], vec!["c"], vec!["d"], vec!["e"]]
+        );
+    }
+
+    #[test]
+    fn stable() {
+        let nodes = ["c", "b", "a"];
+        let edges = [("a", "c")];
+        assert_eq!(
+            stable_topo_sort(&nodes, &edges, Dangling::Error).unwrap(),
+            ["a", "c", "b"]
+        );
+    }
+}